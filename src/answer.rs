@@ -0,0 +1,65 @@
+use tokio_stream::Stream;
+
+use crate::{
+    ollama::{SimpleOllama, TokenResult},
+    prompt::RetrievedDocument,
+};
+
+const SYSTEM: &str = "You answer questions about a Rust crate using only its documentation \
+given by the user. Every sentence relying on a document cites its item path in square \
+brackets, for example [bevy_ecs::world::World]. If the documentation doesn't answer the \
+question, say so instead of guessing.";
+
+#[derive(Debug, Clone)]
+pub struct Answer {
+    pub text: String,
+    /// Paths of the retrieved documents the answer cites, in retrieval order.
+    pub citations: Vec<String>,
+}
+
+impl Answer {
+    pub fn new(text: String, documents: &[RetrievedDocument]) -> Self {
+        let citations = documents
+            .iter()
+            .filter(|document| text.contains(&format!("[{}]", document.path)))
+            .map(|document| document.path.clone())
+            .collect();
+        Answer { text, citations }
+    }
+}
+
+/// The prompt grounding `question` in the retrieved `documents`.
+pub fn build_prompt(question: &str, documents: &[RetrievedDocument]) -> String {
+    let mut prompt = String::from("Documentation:\n\n");
+    for document in documents {
+        prompt.push_str(&format!(
+            "--- [{}] ({} in crate {})\n{}\n\n",
+            document.path, document.kind, document.krate, document.text
+        ));
+    }
+    prompt.push_str(&format!("Question: {}\n", question));
+    prompt
+}
+
+pub async fn answer(
+    ollama: &SimpleOllama,
+    question: &str,
+    documents: &[RetrievedDocument],
+) -> Result<Answer, Box<dyn std::error::Error>> {
+    let text = ollama
+        .generate(SYSTEM, &build_prompt(question, documents))
+        .await?;
+    Ok(Answer::new(text, documents))
+}
+
+/// Same as [`answer`], yielding the text as it is generated. Build the [`Answer`] from the
+/// concatenated tokens with [`Answer::new`] to get the citations.
+pub async fn answer_stream(
+    ollama: &SimpleOllama,
+    question: &str,
+    documents: &[RetrievedDocument],
+) -> Result<impl Stream<Item = TokenResult> + Send + Unpin, Box<dyn std::error::Error>> {
+    ollama
+        .generate_stream(SYSTEM, &build_prompt(question, documents))
+        .await
+}
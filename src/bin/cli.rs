@@ -0,0 +1,241 @@
+use std::error::Error;
+
+use clap::{Parser, Subcommand};
+use doc_explorer::{
+    collections,
+    config::{Config, Distance, Renderer},
+    eval::{evaluate, GoldenQuery},
+    prompt::RetrieveOptions,
+};
+
+#[derive(Parser)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Scores retrieval against a golden set of queries, for every combination of embedding
+    /// model, distance and renderer, or for the given collections. Collections must already be
+    /// indexed.
+    Eval {
+        /// JSON file with a list of `{"query": "...", "expected": ["bevy::ecs::..."]}`.
+        #[arg(long)]
+        queries: String,
+        /// Collection to evaluate with the config recorded in its metadata, can be repeated.
+        /// Replaces the combinations of models, distances and renderers.
+        #[arg(long = "collection", conflicts_with_all = ["models", "distances", "renderers"])]
+        collections: Vec<String>,
+        /// Embedding model to evaluate, can be repeated.
+        #[arg(long = "model")]
+        models: Vec<String>,
+        /// Distance to evaluate (l2, ip or cosine), can be repeated.
+        #[arg(long = "distance")]
+        distances: Vec<Distance>,
+        /// Renderer the documents were generated with (prose or markdown), can be repeated.
+        #[arg(long = "renderer")]
+        renderers: Vec<Renderer>,
+        #[arg(long)]
+        target: Option<String>,
+        /// Number of documents retrieved per query, the k of recall@k and nDCG@k.
+        #[arg(long)]
+        top_k: Option<usize>,
+    },
+    /// Manages the Chroma collections holding the embeddings.
+    Collections {
+        #[command(subcommand)]
+        command: CollectionsCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum CollectionsCommand {
+    /// Lists the collections with the config they were indexed with.
+    List,
+    /// Deletes a collection.
+    Delete { name: String },
+    /// Sets the target, embedding model and distance of the project config to the ones of a
+    /// collection.
+    Switch { name: String },
+    /// Renames the collections named with the hash used before names were made stable.
+    Migrate,
+    /// Assigns a collection indexed with the project config under another name to it.
+    Adopt { name: String },
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    match Cli::parse().command {
+        Command::Eval {
+            queries,
+            collections,
+            models,
+            distances,
+            renderers,
+            target,
+            top_k,
+        } => {
+            eval(
+                &queries,
+                collections,
+                models,
+                distances,
+                renderers,
+                target,
+                top_k,
+            )
+            .await
+        }
+        Command::Collections { command } => manage_collections(command).await,
+    }
+}
+
+async fn manage_collections(command: CollectionsCommand) -> Result<(), Box<dyn Error>> {
+    let config = Config::load()?;
+    match command {
+        CollectionsCommand::List => {
+            println!(
+                "  {:<42} {:<16} {:<30} {:<8} {:>9} {:<14}",
+                "collection", "target", "model", "distance", "documents", "indexed"
+            );
+            for collection in collections::list(&config.chroma).await? {
+                println!(
+                    "{} {:<42} {:<16} {:<30} {:<8} {:>9} {:<14}",
+                    if collection.is_active(&config) {
+                        "*"
+                    } else {
+                        " "
+                    },
+                    collections::elide(&collection.name, 42),
+                    collection.target.as_deref().unwrap_or("?"),
+                    collection.embedding_model.as_deref().unwrap_or("?"),
+                    collection
+                        .distance
+                        .map_or("?", |distance| distance.as_space()),
+                    collection.documents,
+                    collection
+                        .indexed_at
+                        .map_or("?".to_string(), collections::ago)
+                );
+            }
+        }
+        CollectionsCommand::Delete { name } => {
+            collections::delete(&config.chroma, &name).await?;
+            println!("Deleted {}", name);
+        }
+        CollectionsCommand::Switch { name } => {
+            let collection = collections::list(&config.chroma)
+                .await?
+                .into_iter()
+                .find(|collection| collection.name == name)
+                .ok_or_else(|| format!("No collection {}", name))?;
+            collection.apply(&config)?.save()?;
+            println!("Switched to {}", name);
+        }
+        CollectionsCommand::Migrate => {
+            let renamed = collections::migrate(&config).await?;
+            for (old, new) in &renamed {
+                println!("Renamed {} to {}", old, new);
+            }
+            if renamed.is_empty() {
+                println!("Nothing to migrate");
+            }
+        }
+        CollectionsCommand::Adopt { name } => {
+            let new_name = collections::adopt(&config, &name).await?;
+            println!("Adopted {} as {}", name, new_name);
+        }
+    }
+    Ok(())
+}
+
+async fn eval(
+    queries: &str,
+    names: Vec<String>,
+    models: Vec<String>,
+    distances: Vec<Distance>,
+    renderers: Vec<Renderer>,
+    target: Option<String>,
+    top_k: Option<usize>,
+) -> Result<(), Box<dyn Error>> {
+    let queries = GoldenQuery::read(queries)?;
+    let mut base = Config::load()?;
+    if let Some(target) = target {
+        base.target = target;
+    }
+    let mut defaults = RetrieveOptions::default();
+    if let Some(top_k) = top_k {
+        defaults.top_k = top_k;
+    }
+
+    // The collections to evaluate, with the config they were indexed with.
+    let mut runs = vec![];
+    if names.is_empty() {
+        let models = if models.is_empty() {
+            vec![base.embedding_model.clone()]
+        } else {
+            models
+        };
+        let distances = if distances.is_empty() {
+            vec![base.distance]
+        } else {
+            distances
+        };
+        let renderers = if renderers.is_empty() {
+            vec![base.renderer]
+        } else {
+            renderers
+        };
+        for model in &models {
+            for distance in &distances {
+                for renderer in &renderers {
+                    let config = Config {
+                        embedding_model: model.clone(),
+                        distance: *distance,
+                        renderer: *renderer,
+                        ..base.clone()
+                    };
+                    runs.push((config.as_db_name(), config));
+                }
+            }
+        }
+    } else {
+        let known = collections::list(&base.chroma).await?;
+        for name in names {
+            let collection = known
+                .iter()
+                .find(|collection| collection.name == name)
+                .ok_or_else(|| format!("No collection {}", name))?;
+            runs.push((name, collection.apply(&base)?));
+        }
+    }
+
+    println!(
+        "{:<30} {:<8} {:<8} {:<48} {:>9} {:>6} {:>7}",
+        "model",
+        "distance",
+        "renderer",
+        "collection",
+        format!("recall@{}", defaults.top_k),
+        "MRR",
+        format!("nDCG@{}", defaults.top_k)
+    );
+    for (collection, config) in &runs {
+        print!(
+            "{:<30} {:<8} {:<8} {:<48} ",
+            config.embedding_model,
+            config.distance.as_space(),
+            config.renderer.as_str(),
+            collection
+        );
+        match evaluate(config, collection, &queries, &defaults).await {
+            Ok(report) => println!(
+                "{:>9.3} {:>6.3} {:>7.3}",
+                report.mean.recall, report.mean.reciprocal_rank, report.mean.ndcg
+            ),
+            Err(err) => println!("{}", err),
+        }
+    }
+    Ok(())
+}
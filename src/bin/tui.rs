@@ -1,14 +1,11 @@
-use std::{
-    error::Error,
-    hash::{DefaultHasher, Hash, Hasher},
-};
+use std::error::Error;
 
 use bevy::{
     log::{Level, LogPlugin},
     DefaultPlugins,
 };
-use chromadb::v2::ChromaClient;
-use ollama_rs::Ollama;
+use doc_explorer::{health::Health, process::ProcessFailed};
+use ratatecs::prelude::*;
 
 #[derive(Debug, Default)]
 struct CurrentState {
@@ -19,68 +16,18 @@ struct CurrentState {
 }
 
 impl CurrentState {
-    async fn check() -> Self {
-        let chroma: ChromaClient = ChromaClient::new(Default::default());
-        let ollama = Ollama::default();
-
+    fn check(health: &Health) -> Self {
         Self {
             jsons: std::fs::read_dir("./jsons").is_ok(),
             docs: std::fs::read_dir("./docs").is_ok(),
-            db: chroma.list_collections().await.is_ok(),
-            ollama: ollama.list_local_models().await.is_ok(),
-        }
-    }
-
-    async fn check_embedding_model(config: &Config) -> bool {
-        let ollama = Ollama::default();
-        let models = ollama.list_local_models().await.unwrap();
-
-        for model in models {
-            if model.name == config.embedding_model {
-                return true;
-            }
+            db: health.chroma.is_up(),
+            ollama: health.ollama.is_up(),
         }
-        return false;
-    }
-
-    async fn check_vector_db(config: &Config) -> bool {
-        let chroma: ChromaClient = ChromaClient::new(Default::default());
-
-        chroma.get_collection(&config.as_db_name()).await.is_ok()
-    }
-}
-
-#[derive(Debug, Clone, Hash)]
-enum Distance {
-    SquaredL2,
-    InnerProduct,
-    Cosine,
-}
-
-#[derive(Resource, Debug, Clone, Hash)]
-struct Config {
-    target: String,
-    embedding_model: String,
-    distance: Distance,
-}
-
-impl Config {
-    fn as_db_name(&self) -> String {
-        let mut hash = DefaultHasher::new();
-        self.hash(&mut hash);
-        hash.finish().to_string()
     }
 }
 
-impl Default for Config {
-    fn default() -> Self {
-        Self {
-            target: "bevy".to_string(),
-            embedding_model: "nomic-embed-text:latest".to_string(),
-            distance: Distance::SquaredL2,
-        }
-    }
-}
+#[derive(Resource, Debug, Clone, Default, Deref, DerefMut)]
+struct Config(doc_explorer::config::Config);
 
 fn main() -> Result<(), Box<dyn Error>> {
     App::new()
@@ -91,6 +38,8 @@ fn main() -> Result<(), Box<dyn Error>> {
             }),
             bevy_tokio_tasks::TokioTasksPlugin::default(),
             TuiPlugin,
+        ))
+        .add_plugins((
             app::panel,
             status::panel,
             actions::panel,
@@ -99,12 +48,15 @@ fn main() -> Result<(), Box<dyn Error>> {
             download_model::panel,
             generate_embeddings::panel,
             prompt::panel,
-            unimplemented::panel::<1>,
-            unimplemented::panel::<4>,
-            unimplemented::panel::<5>,
+            change_config::panel,
+            start_service::panel::<start_service::Chroma>,
+            start_service::panel::<start_service::Ollama>,
+            failure::panel,
+            browse::panel,
+            collections::panel,
         ))
         .init_state::<CurrentAction>()
-        .insert_resource(Config::default())
+        .insert_resource(Config(doc_explorer::config::Config::load()?))
         .run();
 
     Ok(())
@@ -113,25 +65,86 @@ fn main() -> Result<(), Box<dyn Error>> {
 struct RagState {
     services: CurrentState,
     model: bool,
+    chat_model: bool,
     db: bool,
+    health: Health,
 }
 
-use ratatecs::prelude::*;
+/// A failed action, shown until dismissed.
+#[derive(Resource, Debug)]
+struct Failure {
+    action: &'static str,
+    /// The error followed by what caused it.
+    chain: Vec<String>,
+    /// Last lines written by the failed process, if any.
+    output: Vec<String>,
+}
+
+impl Failure {
+    fn new(action: &'static str, err: &(dyn Error + 'static)) -> Self {
+        Self {
+            action,
+            chain: std::iter::successors(Some(err), |err| err.source())
+                .map(|err| err.to_string())
+                .collect(),
+            output: std::iter::successors(Some(err), |err| err.source())
+                .find_map(|err| err.downcast_ref::<ProcessFailed>())
+                .map(|failed| failed.output.clone())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Shows the failure in place of the current action.
+    fn report(self, world: &mut World) {
+        world.insert_resource(self);
+        world
+            .resource_mut::<NextState<CurrentAction>>()
+            .set(CurrentAction::Failed);
+    }
+}
 
 mod app {
+    use std::time::{Duration, Instant};
+
     use bevy_tokio_tasks::TokioTasksRuntime;
+    use doc_explorer::health;
     use ratatecs::prelude::*;
     use ratatui::widgets::Block;
     use symbols::border;
+    use tokio::task::JoinHandle;
 
-    use crate::{Config, CurrentState, RagState};
+    use crate::{Config, CurrentAction, CurrentState, RagState};
+
+    /// How often the services are checked when the config doesn't change.
+    const HEALTH_INTERVAL: Duration = Duration::from_secs(5);
 
     pub fn panel(app: &mut App) {
-        app.add_systems(Update, (exit_on_esc, update_state));
+        app.add_systems(Startup, enable_bracketed_paste);
+        app.add_systems(Update, (exit_on_esc.run_if(esc_quits), update_state));
+        app.add_systems(Last, disable_bracketed_paste);
 
         app.add_systems(PostUpdate, render);
     }
 
+    /// Pasted text then comes as a single [`event::Event::Paste`] instead of key presses.
+    fn enable_bracketed_paste() {
+        let _ = crossterm::execute!(std::io::stdout(), crossterm::event::EnableBracketedPaste);
+    }
+
+    fn disable_bracketed_paste(mut exit: EventReader<AppExit>) {
+        if exit.read().next().is_some() {
+            let _ = crossterm::execute!(std::io::stdout(), crossterm::event::DisableBracketedPaste);
+        }
+    }
+
+    /// Panels where <Esc> goes back to the menu instead of quitting.
+    fn esc_quits(state: Res<State<CurrentAction>>) -> bool {
+        !matches!(
+            state.get(),
+            CurrentAction::ChangeConfig | CurrentAction::Prompt | CurrentAction::Browse
+        )
+    }
+
     fn exit_on_esc(event: Res<BackendEvent>, mut exit: EventWriter<AppExit>) {
         if let Some(event) = &event.0 {
             if let event::Event::Key(key_event) = event {
@@ -142,19 +155,32 @@ mod app {
         }
     }
 
-    fn update_state(runtime: ResMut<TokioTasksRuntime>, config: Res<Config>) {
+    fn update_state(
+        runtime: ResMut<TokioTasksRuntime>,
+        config: Res<Config>,
+        mut last_check: Local<Option<Instant>>,
+        mut check: Local<Option<JoinHandle<()>>>,
+    ) {
+        // A change of config restarts the check, otherwise a slow check isn't piled up on.
         if !config.is_changed() {
-            return;
+            let due = last_check.is_none_or(|last| last.elapsed() >= HEALTH_INTERVAL);
+            if !due || check.as_ref().is_some_and(|check| !check.is_finished()) {
+                return;
+            }
         }
-        runtime.spawn_background_task(|mut ctx| async move {
-            let state = CurrentState::check().await;
-            let config = Config::default();
-            let model = CurrentState::check_embedding_model(&config).await;
-            let db = CurrentState::check_vector_db(&config).await;
+        if let Some(check) = check.take() {
+            check.abort();
+        }
+        *last_check = Some(Instant::now());
+        let config = config.clone();
+        *check = Some(runtime.spawn_background_task(|mut ctx| async move {
+            let health = health::check(&config).await;
             let state = RagState {
-                services: state,
-                model,
-                db,
+                services: CurrentState::check(&health),
+                model: health.embedding_model,
+                chat_model: health.chat_model,
+                db: health.collection,
+                health,
             };
 
             ctx.run_on_main_thread(move |ctx| {
@@ -162,7 +188,7 @@ mod app {
                 world.insert_resource(state);
             })
             .await;
-        });
+        }));
     }
 
     fn render(mut drawer: WidgetDrawer) {
@@ -181,6 +207,9 @@ mod app {
 }
 
 mod status {
+    use std::time::SystemTime;
+
+    use doc_explorer::{collections, health::ServiceHealth};
     use ratatecs::prelude::*;
     use ratatui::widgets::{Block, List};
     use symbols::border;
@@ -196,7 +225,7 @@ mod status {
         let mut area = frame.area();
         area.x += 1;
         area.y += 1;
-        area.height = 9;
+        area.height = 10;
         area.width -= 2;
 
         let title = Line::from(" Status ".bold());
@@ -224,6 +253,7 @@ mod status {
                 true => "✅".green(),
                 false => "❌".red(),
             },
+            service(&state.health.ollama).dark_gray(),
         ])]);
         let model_state = Text::from(vec![Line::from(vec![
             format!("{:<30}", "model in ollama: ").into(),
@@ -232,12 +262,20 @@ mod status {
                 false => "❌".red(),
             },
         ])]);
+        let chat_model_state = Text::from(vec![Line::from(vec![
+            format!("{:<30}", "chat model in ollama: ").into(),
+            match state.chat_model {
+                true => "✅".green(),
+                false => "❌".red(),
+            },
+        ])]);
         let database_state = Text::from(vec![Line::from(vec![
             format!("{:<30}", "database: ").into(),
             match state.services.db {
                 true => "✅".green(),
                 false => "❌".red(),
             },
+            service(&state.health.chroma).dark_gray(),
         ])]);
         let embeddings_state = Text::from(vec![Line::from(vec![
             format!("{:<30}", "embeddings available: ").into(),
@@ -245,8 +283,9 @@ mod status {
                 true => "✅".green(),
                 false => "❌".red(),
             },
+            collection(state.health.documents, state.health.indexed_at).dark_gray(),
         ])]);
-        let config = Text::from(vec![Line::from(vec![format!("{:?}", *config).yellow()])]);
+        let config = Text::from(vec![Line::from(vec![format!("{:?}", config.0).yellow()])]);
 
         drawer.push_widget(
             Box::new(
@@ -255,6 +294,7 @@ mod status {
                     docs_state,
                     ollama_state,
                     model_state,
+                    chat_model_state,
                     database_state,
                     embeddings_state,
                     config,
@@ -265,6 +305,32 @@ mod status {
             1,
         );
     }
+
+    /// Version and latency of a service that answered.
+    fn service(health: &ServiceHealth) -> String {
+        match (&health.version, health.latency) {
+            (Some(version), Some(latency)) => {
+                format!("  v{} ({} ms)", version, latency.as_millis())
+            }
+            (None, Some(latency)) => format!("  ({} ms)", latency.as_millis()),
+            (_, None) => String::new(),
+        }
+    }
+
+    fn collection(documents: Option<usize>, indexed_at: Option<SystemTime>) -> String {
+        let mut details = vec![];
+        if let Some(documents) = documents {
+            details.push(format!("{} documents", documents));
+        }
+        if let Some(indexed_at) = indexed_at {
+            details.push(format!("indexed {}", collections::ago(indexed_at)));
+        }
+        if details.is_empty() {
+            String::new()
+        } else {
+            format!("  {}", details.join(", "))
+        }
+    }
 }
 
 #[derive(States, Debug, Clone, Copy, Hash, PartialEq, Eq, Default)]
@@ -277,24 +343,12 @@ enum CurrentAction {
     StartDb,
     StartOllama,
     DownloadModel,
+    DownloadChatModel,
     GenerateEmbeddings,
     Prompt,
-}
-
-impl CurrentAction {
-    fn from_u8(i: u8) -> Self {
-        match i {
-            0 => CurrentAction::Menu,
-            1 => CurrentAction::ChangeConfig,
-            2 => CurrentAction::GenerateJsons,
-            3 => CurrentAction::GenerateDocs,
-            4 => CurrentAction::StartDb,
-            5 => CurrentAction::StartOllama,
-            6 => CurrentAction::DownloadModel,
-            7 => CurrentAction::GenerateEmbeddings,
-            _ => CurrentAction::Menu,
-        }
-    }
+    Browse,
+    Collections,
+    Failed,
 }
 
 mod actions {
@@ -343,6 +397,10 @@ mod actions {
                 "Generate Documents from JSONs".to_string(),
                 CurrentAction::GenerateDocs,
             ));
+            list_state.list.push((
+                format!("Browse Items of {}", config.target),
+                CurrentAction::Browse,
+            ));
         }
 
         if state.services.ollama && !state.model {
@@ -351,13 +409,23 @@ mod actions {
                 CurrentAction::DownloadModel,
             ));
         }
+        if state.services.ollama && !state.chat_model {
+            list_state.list.push((
+                format!("Download Chat Model {}", config.chat_model),
+                CurrentAction::DownloadChatModel,
+            ));
+        }
 
         if !state.services.ollama {
             list_state
                 .list
                 .push(("Start Ollama".to_string(), CurrentAction::StartOllama));
         }
-        if !state.services.db {
+        if state.services.db {
+            list_state
+                .list
+                .push(("Manage Collections".to_string(), CurrentAction::Collections));
+        } else {
             list_state
                 .list
                 .push(("Start Vector Database".to_string(), CurrentAction::StartDb));
@@ -407,8 +475,11 @@ mod actions {
         let frame = drawer.get_frame();
         let mut area = frame.area();
         area.x += 15;
-        area.y = (area.height / 2 - 10).max(10);
-        area.height = 8;
+        area.y = (area.height / 2 - 10).max(11);
+        // One row per action, as long as the frame has room.
+        area.height = (list_state.list.len() as u16 + 2)
+            .min(frame.area().height.saturating_sub(area.y + 1))
+            .max(3);
         area.width -= 30;
 
         let title = Line::from(" Actions ".bold());
@@ -425,15 +496,23 @@ mod actions {
 
         let width = area.width as usize - 10;
 
-        let actions = list_state.list.iter().enumerate().map(|(i, action)| {
-            if i == list_state.selected {
-                Line::from(format!(" >> {:^width$} <<", action.0, width = width))
-                    .style(Style::new().italic())
-                    .green()
-            } else {
-                Line::from(format!("    {:^width$}", action.0, width = width))
-            }
-        });
+        // Scrolls to keep the selected action in view.
+        let visible = area.height.saturating_sub(2).max(1) as usize;
+        let skip = list_state.selected.saturating_sub(visible - 1);
+        let actions = list_state
+            .list
+            .iter()
+            .enumerate()
+            .skip(skip)
+            .map(|(i, action)| {
+                if i == list_state.selected {
+                    Line::from(format!(" >> {:^width$} <<", action.0, width = width))
+                        .style(Style::new().italic())
+                        .green()
+                } else {
+                    Line::from(format!("    {:^width$}", action.0, width = width))
+                }
+            });
 
         drawer.push_widget(
             Box::new(List::new(actions).block(block).style(Style::new().white())),
@@ -443,50 +522,377 @@ mod actions {
     }
 }
 
-mod generate_jsons {
-    use doc_explorer::json_generator::generate_jsons;
+mod change_config {
+    use bevy_tokio_tasks::TokioTasksRuntime;
+    use doc_explorer::config::{Distance, Renderer, PROJECT_CONFIG_PATH};
     use ratatecs::prelude::*;
-    use ratatui::widgets::{Block, Clear, Paragraph};
+    use ratatui::widgets::{Block, Clear, List};
     use symbols::border;
 
-    use crate::{Config, CurrentAction};
+    use crate::{prompt::LineEditor, Config, CurrentAction};
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Field {
+        Target,
+        EmbeddingModel,
+        ChatModel,
+        Distance,
+        Renderer,
+        ChromaUrl,
+        ChromaAuthToken,
+        ChromaTenant,
+        ChromaDatabase,
+        ChromaCommand,
+        ChromaPersistDir,
+        OllamaHost,
+        OllamaPort,
+        OllamaBinary,
+        OllamaModelsDir,
+        Apply,
+        Cancel,
+    }
+
+    const FIELDS: [Field; 17] = [
+        Field::Target,
+        Field::EmbeddingModel,
+        Field::ChatModel,
+        Field::Distance,
+        Field::Renderer,
+        Field::ChromaUrl,
+        Field::ChromaAuthToken,
+        Field::ChromaTenant,
+        Field::ChromaDatabase,
+        Field::ChromaCommand,
+        Field::ChromaPersistDir,
+        Field::OllamaHost,
+        Field::OllamaPort,
+        Field::OllamaBinary,
+        Field::OllamaModelsDir,
+        Field::Apply,
+        Field::Cancel,
+    ];
+
+    impl Field {
+        fn label(&self) -> &'static str {
+            match self {
+                Field::Target => "target",
+                Field::EmbeddingModel => "embedding model",
+                Field::ChatModel => "chat model",
+                Field::Distance => "distance",
+                Field::Renderer => "renderer",
+                Field::ChromaUrl => "chroma url",
+                Field::ChromaAuthToken => "chroma auth token",
+                Field::ChromaTenant => "chroma tenant",
+                Field::ChromaDatabase => "chroma database",
+                Field::ChromaCommand => "chroma command",
+                Field::ChromaPersistDir => "chroma data dir",
+                Field::OllamaHost => "ollama host",
+                Field::OllamaPort => "ollama port",
+                Field::OllamaBinary => "ollama binary",
+                Field::OllamaModelsDir => "ollama models dir",
+                Field::Apply => "Apply",
+                Field::Cancel => "Cancel",
+            }
+        }
+
+        fn value(&self, config: &doc_explorer::config::Config) -> String {
+            match self {
+                Field::Target => config.target.clone(),
+                Field::EmbeddingModel => config.embedding_model.clone(),
+                Field::ChatModel => config.chat_model.clone(),
+                Field::Distance => format!("{:?}", config.distance),
+                Field::Renderer => format!("{:?}", config.renderer),
+                Field::ChromaUrl => config.chroma.url.clone(),
+                Field::ChromaAuthToken => config.chroma.auth_token.clone().unwrap_or_default(),
+                Field::ChromaTenant => config.chroma.tenant.clone(),
+                Field::ChromaDatabase => config.chroma.database.clone(),
+                Field::ChromaCommand => config.chroma.command.clone(),
+                Field::ChromaPersistDir => config.chroma.persist_dir.clone(),
+                Field::OllamaHost => config.ollama.host.clone(),
+                Field::OllamaPort => config.ollama.port.to_string(),
+                Field::OllamaBinary => config.ollama.binary.clone(),
+                Field::OllamaModelsDir => config.ollama.models_dir.clone().unwrap_or_default(),
+                Field::Apply | Field::Cancel => String::new(),
+            }
+        }
+
+        fn set(&self, config: &mut doc_explorer::config::Config, value: String) {
+            match self {
+                Field::Target => config.target = value,
+                Field::EmbeddingModel => config.embedding_model = value,
+                Field::ChatModel => config.chat_model = value,
+                Field::ChromaUrl => config.chroma.url = value,
+                Field::ChromaTenant => config.chroma.tenant = value,
+                Field::ChromaDatabase => config.chroma.database = value,
+                Field::ChromaCommand => config.chroma.command = value,
+                Field::ChromaPersistDir => config.chroma.persist_dir = value,
+                Field::OllamaHost => config.ollama.host = value,
+                Field::OllamaBinary => config.ollama.binary = value,
+                Field::OllamaModelsDir => {
+                    config.ollama.models_dir = Some(value).filter(|dir| !dir.is_empty())
+                }
+                Field::OllamaPort => {
+                    if let Ok(port) = value.parse() {
+                        config.ollama.port = port;
+                    }
+                }
+                Field::Distance
+                | Field::Renderer
+                | Field::ChromaAuthToken
+                | Field::Apply
+                | Field::Cancel => (),
+            }
+        }
+
+        /// The auth token isn't editable: it is never written to the project config, which may
+        /// be committed.
+        fn is_text(&self) -> bool {
+            !matches!(
+                self,
+                Field::Distance
+                    | Field::Renderer
+                    | Field::ChromaAuthToken
+                    | Field::Apply
+                    | Field::Cancel
+            )
+        }
+    }
+
+    /// The config being edited, only written to [`Config`] when applied.
+    #[derive(Resource)]
+    struct ConfigForm {
+        config: doc_explorer::config::Config,
+        selected: usize,
+        /// Text of the selected field while it is being edited.
+        editing: Option<LineEditor>,
+        /// Why the config couldn't be applied or saved.
+        save_error: Option<String>,
+    }
+
+    /// Models available in Ollama, to pick from with <Left> / <Right>.
+    #[derive(Resource, Default)]
+    struct LocalModels(Vec<String>);
 
     pub fn panel(app: &mut App) {
-        app.add_systems(Update, exit.run_if(in_state(CurrentAction::GenerateJsons)));
-        app.add_systems(OnExit(CurrentAction::GenerateJsons), work);
+        app.add_systems(OnEnter(CurrentAction::ChangeConfig), setup);
+        app.add_systems(Update, input.run_if(in_state(CurrentAction::ChangeConfig)));
         app.add_systems(
             PostUpdate,
-            render.run_if(in_state(CurrentAction::GenerateJsons)),
+            render.run_if(in_state(CurrentAction::ChangeConfig)),
         );
     }
 
-    fn exit(mut next_state: ResMut<NextState<CurrentAction>>) {
-        next_state.set(CurrentAction::Menu);
+    fn setup(mut commands: Commands, config: Res<Config>, runtime: ResMut<TokioTasksRuntime>) {
+        commands.insert_resource(ConfigForm {
+            config: config.0.clone(),
+            selected: 0,
+            editing: None,
+            save_error: None,
+        });
+        commands.insert_resource(LocalModels::default());
+        let ollama = config.ollama.client();
+        runtime.spawn_background_task(|mut ctx| async move {
+            let mut models = match ollama.list_local_models().await {
+                Ok(models) => models.into_iter().map(|model| model.name).collect(),
+                Err(_) => vec![],
+            };
+            models.sort();
+
+            ctx.run_on_main_thread(move |ctx| {
+                let world: &mut World = ctx.world;
+                world.insert_resource(LocalModels(models));
+            })
+            .await;
+        });
+    }
+
+    fn input(
+        event: Res<BackendEvent>,
+        mut form: ResMut<ConfigForm>,
+        models: Res<LocalModels>,
+        mut config: ResMut<Config>,
+        mut next_state: ResMut<NextState<CurrentAction>>,
+    ) {
+        if let (Some(event::Event::Paste(text)), Some(editor)) = (&event.0, form.editing.as_mut()) {
+            editor.insert_str(&text.replace(['\r', '\n'], ""));
+            return;
+        }
+        let Some(event::Event::Key(key_event)) = &event.0 else {
+            return;
+        };
+        let field = FIELDS[form.selected];
+
+        if let Some(editor) = form.editing.as_mut() {
+            match key_event.code {
+                event::KeyCode::Enter => {
+                    let text = editor.take();
+                    form.editing = None;
+                    field.set(&mut form.config, text);
+                }
+                event::KeyCode::Esc => form.editing = None,
+                _ => {
+                    editor.input(key_event);
+                }
+            }
+            return;
+        }
+
+        match key_event.code {
+            event::KeyCode::Esc => next_state.set(CurrentAction::Menu),
+            event::KeyCode::Up => form.selected = form.selected.saturating_sub(1),
+            event::KeyCode::Down => form.selected = (form.selected + 1).min(FIELDS.len() - 1),
+            event::KeyCode::Left | event::KeyCode::Right => {
+                let forward = key_event.code == event::KeyCode::Right;
+                match field {
+                    Field::Distance => {
+                        form.config.distance = cycle(&Distance::ALL, &form.config.distance, forward)
+                    }
+                    Field::Renderer => {
+                        form.config.renderer = cycle(&Renderer::ALL, &form.config.renderer, forward)
+                    }
+                    Field::EmbeddingModel | Field::ChatModel if !models.0.is_empty() => {
+                        let model = cycle(&models.0, &field.value(&form.config), forward);
+                        field.set(&mut form.config, model);
+                    }
+                    _ => (),
+                }
+            }
+            event::KeyCode::Enter => match field {
+                Field::Apply => {
+                    if let Err(err) = form
+                        .config
+                        .ollama
+                        .validate()
+                        .and_then(|()| form.config.chroma.validate())
+                    {
+                        form.save_error = Some(err);
+                        return;
+                    }
+                    // Replacing the resource marks it changed, which refreshes the state.
+                    config.0 = form.config.clone();
+                    match form.config.save() {
+                        Ok(()) => next_state.set(CurrentAction::Menu),
+                        Err(err) => {
+                            form.save_error =
+                                Some(format!("Couldn't save {}: {}", PROJECT_CONFIG_PATH, err))
+                        }
+                    }
+                }
+                Field::Cancel => next_state.set(CurrentAction::Menu),
+                field if field.is_text() => {
+                    let mut editor = LineEditor::default();
+                    editor.set(field.value(&form.config));
+                    form.editing = Some(editor);
+                }
+                _ => (),
+            },
+            _ => (),
+        }
     }
 
-    fn work(mut config: ResMut<Config>) {
-        config.set_changed();
-        generate_jsons(config.target.clone());
+    /// The value after (or before) `current` in `values`, wrapping around. Starts from the first
+    /// value when `current` isn't one of them.
+    fn cycle<T: Clone + PartialEq>(values: &[T], current: &T, forward: bool) -> T {
+        let position = values.iter().position(|value| value == current);
+        let next = match (position, forward) {
+            (None, _) => 0,
+            (Some(i), true) => (i + 1) % values.len(),
+            (Some(i), false) => (i + values.len() - 1) % values.len(),
+        };
+        values[next].clone()
     }
 
-    fn render(mut drawer: WidgetDrawer) {
+    fn render(form: Res<ConfigForm>, models: Res<LocalModels>, mut drawer: WidgetDrawer) {
         let frame = drawer.get_frame();
         let mut area = frame.area();
         area.x += 15;
-        area.y = (area.height / 2 - 10).max(10);
-        area.height = 8;
+        area.y = (area.height / 2 - 10).max(11);
+        area.height = FIELDS.len() as u16 + 3;
         area.width -= 30;
 
-        let block = Block::bordered()
-            .title(Line::from("Generate JSONs").bold().centered())
+        let instructions = if form.editing.is_some() {
+            Line::from(vec![
+                " Confirm ".into(),
+                "<Enter> ".blue().bold(),
+                " Cancel ".into(),
+                "<Esc> ".blue().bold(),
+            ])
+        } else {
+            Line::from(vec![
+                " Choose Field ".into(),
+                "<Up> / <Down> ".blue().bold(),
+                " Pick ".into(),
+                "<Left> / <Right> ".blue().bold(),
+                " Edit / Select ".into(),
+                "<Enter> ".blue().bold(),
+                " Back ".into(),
+                "<Esc> ".blue().bold(),
+            ])
+        };
+        let mut block = Block::bordered()
+            .title(Line::from(" Change Config ".bold()).centered())
+            .title_bottom(instructions.right_aligned())
             .border_set(border::THICK);
+        if let Some(save_error) = &form.save_error {
+            block =
+                block.title_bottom(Line::from(format!(" {} ", save_error).red()).left_aligned());
+        }
+
+        let lines = FIELDS.iter().enumerate().map(|(i, field)| {
+            let selected = i == form.selected;
+            let value = match (field, &form.editing) {
+                (_, Some(editor)) if selected => {
+                    let mut line = Line::from(format!(" >> {:<20}", format!("{}:", field.label())));
+                    line.spans.extend(editor.spans());
+                    return line.green();
+                }
+                (Field::ChromaAuthToken, _) => format!(
+                    "{}(set with CHROMA_AUTH_TOKEN or in the user config)",
+                    if form.config.chroma.auth_token.is_some() {
+                        "*** "
+                    } else {
+                        ""
+                    }
+                ),
+                (Field::Distance | Field::Renderer, _) => {
+                    format!("< {} >", field.value(&form.config))
+                }
+                (Field::EmbeddingModel | Field::ChatModel, _) if !models.0.is_empty() => {
+                    let value = field.value(&form.config);
+                    if models.0.contains(&value) {
+                        format!("< {} >", value)
+                    } else {
+                        format!("< {} > (not downloaded)", value)
+                    }
+                }
+                _ => field.value(&form.config),
+            };
+            let line = match field {
+                Field::Apply | Field::Cancel => Line::from(format!(
+                    " {} {}",
+                    if selected { ">>" } else { "  " },
+                    field.label()
+                )),
+                _ => Line::from(format!(
+                    " {} {:<20}{}",
+                    if selected { ">>" } else { "  " },
+                    format!("{}:", field.label()),
+                    value
+                )),
+            };
+            if selected {
+                line.style(Style::new().italic()).green()
+            } else {
+                line
+            }
+        });
 
         drawer.push_widget(Box::new(Clear), area, 1);
         drawer.push_widget(
             Box::new(
-                Paragraph::new(Line::from("Working... (can take a few minutes)").italic())
-                    .centered()
-                    .block(block),
+                List::new(lines.collect::<Vec<_>>())
+                    .block(block)
+                    .style(Style::new().white()),
             ),
             area,
             2,
@@ -494,72 +900,231 @@ mod generate_jsons {
     }
 }
 
-mod generate_docs {
-    use doc_explorer::document::generate_docs;
+mod progress_task {
+    use std::{error::Error, future::Future, pin::Pin, sync::Arc};
+
+    use bevy_tokio_tasks::TokioTasksRuntime;
+    use doc_explorer::progress::Progress;
     use ratatecs::prelude::*;
-    use ratatui::widgets::{Block, Clear, Paragraph};
+    use ratatui::widgets::{Block, Clear, Gauge, Paragraph};
     use symbols::border;
 
-    use crate::{Config, CurrentAction};
+    use crate::{Config, CurrentAction, Failure};
 
-    pub fn panel(app: &mut App) {
-        app.add_systems(Update, exit.run_if(in_state(CurrentAction::GenerateDocs)));
-        app.add_systems(OnExit(CurrentAction::GenerateDocs), work);
+    /// Work run in the background, reporting to the given progress and stopping when it is
+    /// cancelled.
+    pub type Job = fn(
+        &doc_explorer::config::Config,
+        Arc<Progress>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn Error>>> + Send>>;
+
+    #[derive(Resource)]
+    struct Task(Arc<Progress>);
+
+    #[derive(Resource)]
+    struct Done;
+
+    /// Runs `job` when entering `action`, showing its progress under `title` until it is done.
+    pub fn panel(app: &mut App, action: CurrentAction, title: &'static str, job: Job) {
+        app.add_systems(
+            OnEnter(action),
+            move |mut commands: Commands,
+                  runtime: ResMut<TokioTasksRuntime>,
+                  config: Res<Config>| {
+                let progress = Arc::new(Progress::default());
+                commands.insert_resource(Task(progress.clone()));
+                let job = job(&config, progress);
+                runtime.spawn_background_task(move |mut ctx| async move {
+                    let result = job.await.map_err(|err| Failure::new(title, &*err));
+
+                    ctx.run_on_main_thread(move |ctx| {
+                        let world: &mut World = ctx.world;
+                        match result {
+                            Err(failure) if !world.resource::<Task>().0.is_cancelled() => {
+                                failure.report(world)
+                            }
+                            _ => world.insert_resource(Done),
+                        }
+                        world.resource_mut::<Config>().set_changed();
+                    })
+                    .await;
+                });
+            },
+        );
+        app.add_systems(Update, (input, exit).run_if(in_state(action)));
         app.add_systems(
             PostUpdate,
-            render.run_if(in_state(CurrentAction::GenerateDocs)),
+            (move |task: Res<Task>, mut drawer: WidgetDrawer| render(title, &task.0, &mut drawer))
+                .run_if(in_state(action)),
         );
     }
 
-    fn exit(mut next_state: ResMut<NextState<CurrentAction>>) {
-        next_state.set(CurrentAction::Menu);
+    fn input(event: Res<BackendEvent>, task: Res<Task>) {
+        if let Some(event::Event::Key(key_event)) = &event.0 {
+            if key_event.code == event::KeyCode::Char('c')
+                && key_event.modifiers.contains(event::KeyModifiers::CONTROL)
+            {
+                task.0.cancel();
+            }
+        }
     }
 
-    fn work(mut config: ResMut<Config>) {
-        config.set_changed();
-        generate_docs(config.target.clone());
+    fn exit(
+        _done: Res<Done>,
+        mut commands: Commands,
+        mut next_state: ResMut<NextState<CurrentAction>>,
+    ) {
+        commands.remove_resource::<Done>();
+        next_state.set(CurrentAction::Menu);
     }
 
-    fn render(mut drawer: WidgetDrawer) {
+    fn render(title: &'static str, progress: &Progress, drawer: &mut WidgetDrawer) {
         let frame = drawer.get_frame();
         let mut area = frame.area();
         area.x += 15;
-        area.y = (area.height / 2 - 10).max(10);
+        area.y = (area.height / 2 - 10).max(11);
         area.height = 8;
         area.width -= 30;
 
         let block = Block::bordered()
-            .title(Line::from("Generate Documents").bold().centered())
+            .title(Line::from(title).bold().centered())
+            .title_bottom(
+                Line::from(vec![" Cancel ".into(), "<Ctrl-C> ".blue().bold()]).right_aligned(),
+            )
             .border_set(border::THICK);
 
         drawer.push_widget(Box::new(Clear), area, 1);
         drawer.push_widget(
             Box::new(
-                Paragraph::new(Line::from("Working... (can take a few minutes)").italic())
+                Paragraph::new(Line::from(format!("Working... {}", progress.message())).italic())
                     .centered()
                     .block(block),
             ),
             area,
             2,
         );
+
+        if let Some(ratio) = progress.ratio() {
+            let mut gauge_area = area;
+            gauge_area.x += 4;
+            gauge_area.y += 3;
+            gauge_area.height = 1;
+            gauge_area.width -= 8;
+            drawer.push_widget(
+                Box::new(
+                    Gauge::default()
+                        .gauge_style(Style::new().green())
+                        .ratio(ratio),
+                ),
+                gauge_area,
+                3,
+            );
+        }
+    }
+}
+
+mod generate_jsons {
+    use doc_explorer::json_generator::generate_jsons;
+    use ratatecs::prelude::*;
+
+    use crate::{progress_task, CurrentAction};
+
+    pub fn panel(app: &mut App) {
+        progress_task::panel(
+            app,
+            CurrentAction::GenerateJsons,
+            "Generate JSONs",
+            |config, progress| {
+                let target = config.target.clone();
+                Box::pin(async move { generate_jsons(target, &progress).await })
+            },
+        );
+    }
+}
+
+mod generate_docs {
+    use doc_explorer::document::generate_docs;
+    use ratatecs::prelude::*;
+
+    use crate::{progress_task, CurrentAction};
+
+    pub fn panel(app: &mut App) {
+        progress_task::panel(
+            app,
+            CurrentAction::GenerateDocs,
+            "Generate Documents",
+            |config, progress| {
+                let target = config.target.clone();
+                let renderer = config.renderer;
+                Box::pin(async move {
+                    // Reading the JSONs and writing the documents is blocking work.
+                    tokio::task::spawn_blocking(move || {
+                        generate_docs(target, renderer, &progress).map_err(|err| err.to_string())
+                    })
+                    .await??;
+                    Ok(())
+                })
+            },
+        );
     }
 }
 
 mod download_model {
-    use bevy_tokio_tasks::TokioTasksRuntime;
     use doc_explorer::ollama::SimpleOllama;
     use ratatecs::prelude::*;
+
+    use crate::{progress_task, CurrentAction};
+
+    pub fn panel(app: &mut App) {
+        progress_task::panel(
+            app,
+            CurrentAction::DownloadModel,
+            "Download Model",
+            |config, progress| {
+                let ollama = SimpleOllama::new(
+                    &config.ollama,
+                    config.embedding_model.clone(),
+                    config.chat_model.clone(),
+                );
+                let model = config.embedding_model.clone();
+                Box::pin(async move { ollama.download_model(&model, &progress).await })
+            },
+        );
+        progress_task::panel(
+            app,
+            CurrentAction::DownloadChatModel,
+            "Download Chat Model",
+            |config, progress| {
+                let ollama = SimpleOllama::new(
+                    &config.ollama,
+                    config.embedding_model.clone(),
+                    config.chat_model.clone(),
+                );
+                let model = config.chat_model.clone();
+                Box::pin(async move { ollama.download_model(&model, &progress).await })
+            },
+        );
+    }
+}
+
+mod generate_embeddings {
+    use bevy_tokio_tasks::TokioTasksRuntime;
+    use doc_explorer::{embed::generate_embeddings, ollama::SimpleOllama};
+    use ratatecs::prelude::*;
     use ratatui::widgets::{Block, Clear, Paragraph};
     use symbols::border;
 
-    use crate::{Config, CurrentAction};
+    use crate::{Config, CurrentAction, Failure};
 
     pub fn panel(app: &mut App) {
-        app.add_systems(Update, exit.run_if(in_state(CurrentAction::DownloadModel)));
-        app.add_systems(OnEnter(CurrentAction::DownloadModel), work);
+        app.add_systems(
+            Update,
+            exit.run_if(in_state(CurrentAction::GenerateEmbeddings)),
+        );
+        app.add_systems(OnEnter(CurrentAction::GenerateEmbeddings), work);
         app.add_systems(
             PostUpdate,
-            render.run_if(in_state(CurrentAction::DownloadModel)),
+            render.run_if(in_state(CurrentAction::GenerateEmbeddings)),
         );
     }
 
@@ -576,13 +1141,23 @@ mod download_model {
     }
 
     fn work(runtime: ResMut<TokioTasksRuntime>, config: Res<Config>) {
-        let ollama = SimpleOllama::new(config.embedding_model.clone());
+        let ollama = SimpleOllama::new(
+            &config.ollama,
+            config.embedding_model.clone(),
+            config.chat_model.clone(),
+        );
+        let config = config.0.clone();
         runtime.spawn_background_task(|mut ctx| async move {
-            ollama.download_model().await.unwrap();
+            let result = generate_embeddings(ollama, &config)
+                .await
+                .map_err(|err| Failure::new("Generate Embeddings", &*err));
 
             ctx.run_on_main_thread(move |ctx| {
                 let world: &mut World = ctx.world;
-                world.insert_resource(Done);
+                match result {
+                    Ok(()) => world.insert_resource(Done),
+                    Err(failure) => failure.report(world),
+                }
                 world.resource_mut::<Config>().set_changed();
             })
             .await;
@@ -592,12 +1167,12 @@ mod download_model {
         let frame = drawer.get_frame();
         let mut area = frame.area();
         area.x += 15;
-        area.y = (area.height / 2 - 10).max(10);
+        area.y = (area.height / 2 - 10).max(11);
         area.height = 8;
         area.width -= 30;
 
         let block = Block::bordered()
-            .title(Line::from("Download Model").bold().centered())
+            .title(Line::from("Generate Embeddings").bold().centered())
             .border_set(border::THICK);
 
         drawer.push_widget(Box::new(Clear), area, 1);
@@ -613,107 +1188,343 @@ mod download_model {
     }
 }
 
-mod generate_embeddings {
-    use bevy_tokio_tasks::TokioTasksRuntime;
-    use doc_explorer::{embed::generate_embeddings, ollama::SimpleOllama};
+mod prompt {
+    use std::collections::HashSet;
+
+    use bevy_tokio_tasks::{TaskContext, TokioTasksRuntime};
+    use doc_explorer::{
+        answer::{answer_stream, Answer},
+        chroma::ChromaConfig,
+        conversation::Conversation,
+        document::DocumentEntry,
+        ollama::SimpleOllama,
+        prompt::{retrieve, Query, RetrieveOptions, RetrievedDocument},
+    };
     use ratatecs::prelude::*;
-    use ratatui::widgets::{Block, Clear, Paragraph};
+    use ratatui::widgets::{Block, Clear, List, Paragraph, Wrap};
     use symbols::border;
+    use tokio::task::JoinHandle;
+    use tokio_stream::StreamExt;
+
+    use crate::{Config, CurrentAction, Failure, RagState};
+
+    struct Exchange {
+        prompt: String,
+        standalone_prompt: Option<String>,
+        documents: Vec<RetrievedDocument>,
+        answer: Option<Answer>,
+        generating: bool,
+        cancelled: bool,
+    }
 
-    use crate::{Config, CurrentAction};
+    #[derive(Resource)]
+    struct PromptsAndResponses(Vec<Exchange>);
 
-    pub fn panel(app: &mut App) {
-        app.add_systems(
-            Update,
-            exit.run_if(in_state(CurrentAction::GenerateEmbeddings)),
-        );
-        app.add_systems(OnEnter(CurrentAction::GenerateEmbeddings), work);
-        app.add_systems(
-            PostUpdate,
-            render.run_if(in_state(CurrentAction::GenerateEmbeddings)),
-        );
+    #[derive(Resource, Default)]
+    struct CurrentPrompt(LineEditor);
+
+    /// A single line of text with a cursor, counted in chars.
+    #[derive(Default)]
+    pub struct LineEditor {
+        text: String,
+        cursor: usize,
     }
 
-    #[derive(Resource)]
-    struct Done;
+    impl LineEditor {
+        fn byte_index(&self, cursor: usize) -> usize {
+            self.text
+                .char_indices()
+                .nth(cursor)
+                .map_or(self.text.len(), |(i, _)| i)
+        }
 
-    fn exit(
-        _done: Res<Done>,
-        mut commands: Commands,
-        mut next_state: ResMut<NextState<CurrentAction>>,
-    ) {
-        commands.remove_resource::<Done>();
-        next_state.set(CurrentAction::Menu);
+        fn len(&self) -> usize {
+            self.text.chars().count()
+        }
+
+        fn insert(&mut self, c: char) {
+            let i = self.byte_index(self.cursor);
+            self.text.insert(i, c);
+            self.cursor += 1;
+        }
+
+        pub fn insert_str(&mut self, text: &str) {
+            let i = self.byte_index(self.cursor);
+            self.text.insert_str(i, text);
+            self.cursor += text.chars().count();
+        }
+
+        fn backspace(&mut self) {
+            if self.cursor > 0 {
+                self.cursor -= 1;
+                self.text.remove(self.byte_index(self.cursor));
+            }
+        }
+
+        fn delete(&mut self) {
+            if self.cursor < self.len() {
+                self.text.remove(self.byte_index(self.cursor));
+            }
+        }
+
+        fn left(&mut self) {
+            self.cursor = self.cursor.saturating_sub(1);
+        }
+
+        fn right(&mut self) {
+            self.cursor = (self.cursor + 1).min(self.len());
+        }
+
+        fn home(&mut self) {
+            self.cursor = 0;
+        }
+
+        fn end(&mut self) {
+            self.cursor = self.len();
+        }
+
+        /// Moves to the start of the current or previous word.
+        fn word_left(&mut self) {
+            let chars = self.text.chars().collect::<Vec<_>>();
+            let mut i = self.cursor;
+            while i > 0 && chars[i - 1].is_whitespace() {
+                i -= 1;
+            }
+            while i > 0 && !chars[i - 1].is_whitespace() {
+                i -= 1;
+            }
+            self.cursor = i;
+        }
+
+        /// Moves to the start of the next word.
+        fn word_right(&mut self) {
+            let chars = self.text.chars().collect::<Vec<_>>();
+            let mut i = self.cursor;
+            while i < chars.len() && !chars[i].is_whitespace() {
+                i += 1;
+            }
+            while i < chars.len() && chars[i].is_whitespace() {
+                i += 1;
+            }
+            self.cursor = i;
+        }
+
+        fn delete_word(&mut self) {
+            let end = self.byte_index(self.cursor);
+            self.word_left();
+            let start = self.byte_index(self.cursor);
+            self.text.replace_range(start..end, "");
+        }
+
+        pub fn set(&mut self, text: String) {
+            self.cursor = text.chars().count();
+            self.text = text;
+        }
+
+        pub fn take(&mut self) -> String {
+            self.cursor = 0;
+            std::mem::take(&mut self.text)
+        }
+
+        /// Applies an editing key. Returns `false` for keys that don't edit the text.
+        pub fn input(&mut self, key_event: &event::KeyEvent) -> bool {
+            let control = key_event.modifiers.contains(event::KeyModifiers::CONTROL);
+            match key_event.code {
+                event::KeyCode::Char('w') if control => self.delete_word(),
+                event::KeyCode::Char('a') if control => self.home(),
+                event::KeyCode::Char('e') if control => self.end(),
+                event::KeyCode::Char(_) if control => return false,
+                event::KeyCode::Char(x) => self.insert(x),
+                event::KeyCode::Backspace if control => self.delete_word(),
+                event::KeyCode::Backspace => self.backspace(),
+                event::KeyCode::Delete => self.delete(),
+                event::KeyCode::Left if control => self.word_left(),
+                event::KeyCode::Left => self.left(),
+                event::KeyCode::Right if control => self.word_right(),
+                event::KeyCode::Right => self.right(),
+                event::KeyCode::Home => self.home(),
+                event::KeyCode::End => self.end(),
+                _ => return false,
+            }
+            true
+        }
+
+        /// The text with the char under the cursor reversed.
+        pub fn spans(&self) -> Vec<Span<'static>> {
+            let (before, after) = self.text.split_at(self.byte_index(self.cursor));
+            let mut after = after.chars();
+            let under_cursor = after.next().map_or(" ".to_string(), String::from);
+            vec![
+                before.to_string().into(),
+                under_cursor.reversed(),
+                after.as_str().to_string().into(),
+            ]
+        }
     }
 
-    fn work(runtime: ResMut<TokioTasksRuntime>, config: Res<Config>) {
-        let ollama = SimpleOllama::new(config.embedding_model.clone());
-        let db_name = config.as_db_name();
-        let distance = match config.distance {
-            crate::Distance::SquaredL2 => "l2",
-            crate::Distance::InnerProduct => "ip",
-            crate::Distance::Cosine => "cosine",
-        }
-        .to_string();
-        runtime.spawn_background_task(|mut ctx| async move {
-            generate_embeddings(ollama, &db_name, &distance)
-                .await
-                .unwrap();
+    /// Submitted prompts, recalled with <Up> / <Down>. Kept when leaving the panel.
+    #[derive(Resource, Default)]
+    struct PromptHistory {
+        prompts: Vec<String>,
+        recalled: Option<usize>,
+        /// What was being typed before recalling older prompts.
+        draft: String,
+    }
 
-            ctx.run_on_main_thread(move |ctx| {
-                let world: &mut World = ctx.world;
-                world.insert_resource(Done);
-                world.resource_mut::<Config>().set_changed();
-            })
-            .await;
-        });
+    impl PromptHistory {
+        fn push(&mut self, prompt: &str) {
+            self.recalled = None;
+            if !prompt.is_empty() && self.prompts.last().map(String::as_str) != Some(prompt) {
+                self.prompts.push(prompt.to_string());
+            }
+        }
+
+        fn previous(&mut self, editor: &mut LineEditor) {
+            let i = match self.recalled {
+                None if self.prompts.is_empty() => return,
+                None => {
+                    self.draft = editor.text.clone();
+                    self.prompts.len() - 1
+                }
+                Some(i) => i.saturating_sub(1),
+            };
+            self.recalled = Some(i);
+            editor.set(self.prompts[i].clone());
+        }
+
+        fn next(&mut self, editor: &mut LineEditor) {
+            match self.recalled {
+                None => (),
+                Some(i) if i + 1 < self.prompts.len() => {
+                    self.recalled = Some(i + 1);
+                    editor.set(self.prompts[i + 1].clone());
+                }
+                Some(_) => {
+                    self.recalled = None;
+                    editor.set(std::mem::take(&mut self.draft));
+                }
+            }
+        }
     }
-    fn render(mut drawer: WidgetDrawer) {
-        let frame = drawer.get_frame();
-        let mut area = frame.area();
-        area.x += 15;
-        area.y = (area.height / 2 - 10).max(10);
-        area.height = 8;
-        area.width -= 30;
 
-        let block = Block::bordered()
-            .title(Line::from("Generate Embeddings").bold().centered())
-            .border_set(border::THICK);
+    /// The conversation so far, used to rewrite follow-up prompts. Managed with the `/reset`,
+    /// `/save <name>`, `/load <name>` and `/list` prompts.
+    #[derive(Resource, Default)]
+    struct CurrentConversation(Conversation);
+
+    /// Result of the last conversation command.
+    #[derive(Resource, Default)]
+    struct Notice(Option<String>);
+
+    /// The in-flight prompt task, aborted with <Ctrl-C> or when leaving the panel. Cleared by
+    /// the task when it completes.
+    #[derive(Resource, Default)]
+    struct Generation(Option<JoinHandle<()>>);
+
+    /// Options used for prompts that don't override them. A prompt made only of options
+    /// (like `top_k:5`) updates them instead of retrieving.
+    #[derive(Resource, Default)]
+    struct DefaultRetrieveOptions(RetrieveOptions);
+
+    /// Where keys go: the prompt input, the results of the last prompt, or the preview of one.
+    #[derive(Resource, Default, PartialEq, Eq)]
+    enum Focus {
+        #[default]
+        Input,
+        Results,
+        Preview,
+    }
 
-        drawer.push_widget(Box::new(Clear), area, 1);
-        drawer.push_widget(
-            Box::new(
-                Paragraph::new(Line::from("Working... (can take a few minutes)").italic())
-                    .centered()
-                    .block(block),
-            ),
-            area,
-            2,
-        );
+    /// The result of the last prompt being looked at, and its preview.
+    #[derive(Resource, Default)]
+    struct Selection {
+        result: usize,
+        preview: Option<Preview>,
+        /// Previews left by jumping to a related item, reopened with <Backspace>.
+        previous: Vec<Preview>,
     }
-}
 
-mod prompt {
-    use bevy_tokio_tasks::TokioTasksRuntime;
-    use doc_explorer::{ollama::SimpleOllama, prompt::retrieve};
-    use ratatecs::prelude::*;
-    use ratatui::widgets::{Block, Clear, List, Paragraph};
-    use symbols::border;
+    struct Preview {
+        path: String,
+        kind: String,
+        krate: String,
+        text: String,
+        /// Documented items mentioned in the text.
+        related: Vec<DocumentEntry>,
+        selected_related: usize,
+        scroll: u16,
+        /// Highest scroll still showing the end of the text, set when it is rendered.
+        max_scroll: u16,
+    }
 
-    use crate::{Config, CurrentAction};
+    impl Preview {
+        fn new(path: &str, kind: &str, krate: &str, text: String, index: &[DocumentEntry]) -> Self {
+            let words = text
+                .split(|c: char| !c.is_alphanumeric() && c != '_')
+                .collect::<HashSet<_>>();
+            let mut related = index
+                .iter()
+                .filter(|entry| entry.path != path)
+                .filter(|entry| words.contains(entry.path.rsplit("::").next().unwrap_or_default()))
+                .cloned()
+                .collect::<Vec<_>>();
+            related.sort_by(|a, b| a.path.cmp(&b.path));
+            related.dedup_by(|a, b| a.path == b.path);
+            Self {
+                path: path.to_string(),
+                kind: kind.to_string(),
+                krate: krate.to_string(),
+                text,
+                related,
+                selected_related: 0,
+                scroll: 0,
+                max_scroll: 0,
+            }
+        }
 
-    #[derive(Resource)]
-    struct PromptsAndResponses(Vec<(String, Vec<(String, f32)>)>);
+        fn open(entry: &DocumentEntry, index: &[DocumentEntry]) -> Self {
+            let text = std::fs::read_to_string(&entry.file)
+                .unwrap_or_else(|err| format!("Couldn't read {}: {}", entry.file, err));
+            Self::new(&entry.path, &entry.kind, &entry.krate, text, index)
+        }
+    }
 
-    #[derive(Resource)]
-    struct CurrentPrompt(String);
+    /// The generated documents, to find the items related to a preview.
+    #[derive(Resource, Default)]
+    struct DocumentIndex(Vec<DocumentEntry>);
 
     pub fn panel(app: &mut App) {
-        app.add_systems(Update, input.run_if(in_state(CurrentAction::Prompt)));
+        app.init_resource::<DefaultRetrieveOptions>();
+        app.init_resource::<Generation>();
+        app.init_resource::<PromptHistory>();
+        app.add_systems(
+            Update,
+            (
+                input.run_if(resource_equals(Focus::Input)),
+                browse.run_if(not(resource_equals(Focus::Input))),
+            )
+                .chain()
+                .run_if(in_state(CurrentAction::Prompt)),
+        );
         app.add_systems(OnEnter(CurrentAction::Prompt), |mut commands: Commands| {
             commands.insert_resource(PromptsAndResponses(vec![]));
-            commands.insert_resource(CurrentPrompt("".to_string()));
+            commands.insert_resource(CurrentPrompt::default());
+            commands.insert_resource(CurrentConversation::default());
+            commands.insert_resource(Notice::default());
+            commands.insert_resource(Focus::default());
+            commands.insert_resource(Selection::default());
+            commands.insert_resource(DocumentIndex(
+                DocumentEntry::read_index().unwrap_or_default(),
+            ));
         });
+        app.add_systems(
+            OnExit(CurrentAction::Prompt),
+            |mut generation: ResMut<Generation>| {
+                if let Some(task) = generation.0.take() {
+                    task.abort();
+                }
+            },
+        );
         app.add_systems(PostUpdate, render.run_if(in_state(CurrentAction::Prompt)));
     }
 
@@ -721,33 +1532,90 @@ mod prompt {
         event: Res<BackendEvent>,
         mut next_state: ResMut<NextState<CurrentAction>>,
         mut current_prompt: ResMut<CurrentPrompt>,
+        mut prompt_history: ResMut<PromptHistory>,
         runtime: ResMut<TokioTasksRuntime>,
         config: Res<Config>,
+        state: Res<RagState>,
+        mut default_options: ResMut<DefaultRetrieveOptions>,
+        mut generation: ResMut<Generation>,
+        mut history: ResMut<PromptsAndResponses>,
+        mut conversation: ResMut<CurrentConversation>,
+        mut notice: ResMut<Notice>,
+        mut focus: ResMut<Focus>,
     ) {
         if let Some(event) = &event.0 {
+            if let event::Event::Paste(text) = event {
+                current_prompt
+                    .0
+                    .insert_str(&text.replace(['\r', '\n'], " "));
+            }
             if let event::Event::Key(key_event) = event {
+                let control = key_event.modifiers.contains(event::KeyModifiers::CONTROL);
+                let editor = &mut current_prompt.0;
                 match key_event.code {
-                    event::KeyCode::Char(x) => {
-                        current_prompt.0.push(x);
+                    event::KeyCode::Char('c') if control => {
+                        cancel(&mut generation, &mut history);
+                    }
+                    _ if editor.input(key_event) => (),
+                    event::KeyCode::Up => prompt_history.previous(editor),
+                    event::KeyCode::Down => prompt_history.next(editor),
+                    event::KeyCode::Tab => {
+                        if history
+                            .0
+                            .last()
+                            .is_some_and(|exchange| !exchange.documents.is_empty())
+                        {
+                            *focus = Focus::Results;
+                        }
                     }
+                    event::KeyCode::Esc => next_state.set(CurrentAction::Menu),
                     event::KeyCode::Enter => {
-                        if current_prompt.0 == "stop" {
-                            next_state.set(CurrentAction::Menu);
+                        let input = editor.take();
+                        prompt_history.push(&input);
+                        if input.starts_with('/') {
+                            cancel(&mut generation, &mut history);
+                            notice.0 = Some(command(&input, &mut history, &mut conversation));
+                            return;
+                        }
+                        let query = Query::parse(&input, &default_options.0);
+                        if query.text.is_empty() {
+                            default_options.0 = query.options;
+                            return;
                         }
-                        let prompt = current_prompt.0.clone();
-                        current_prompt.0.clear();
-                        let ollama = SimpleOllama::new(config.embedding_model.clone());
+                        cancel(&mut generation, &mut history);
+                        let prompt = query.text;
+                        let options = query.options;
+                        let generate = state.chat_model;
+                        let ollama = SimpleOllama::new(
+                            &config.ollama,
+                            config.embedding_model.clone(),
+                            config.chat_model.clone(),
+                        );
+                        let chroma = config.chroma.clone();
                         let db_name = config.as_db_name();
-                        runtime.spawn_background_task(|mut ctx| async move {
-                            let responses = retrieve(ollama, &db_name, &prompt).await.unwrap();
-
-                            ctx.run_on_main_thread(move |ctx| {
-                                let world: &mut World = ctx.world;
-                                let mut history = world.resource_mut::<PromptsAndResponses>();
-                                history.0.push((prompt, responses));
-                            })
+                        let conversation = conversation.0.clone();
+                        let task = runtime.spawn_background_task(move |mut ctx| async move {
+                            let result = ask(
+                                &mut ctx,
+                                prompt,
+                                options,
+                                generate,
+                                ollama,
+                                chroma,
+                                db_name,
+                                conversation,
+                            )
                             .await;
+                            if let Err(failure) = result {
+                                ctx.run_on_main_thread(move |ctx| {
+                                    let world: &mut World = ctx.world;
+                                    world.resource_mut::<Generation>().0 = None;
+                                    failure.report(world);
+                                })
+                                .await;
+                            }
                         });
+                        generation.0 = Some(task);
                     }
                     _ => (),
                 }
@@ -755,10 +1623,302 @@ mod prompt {
         }
     }
 
+    fn browse(
+        event: Res<BackendEvent>,
+        history: Res<PromptsAndResponses>,
+        index: Res<DocumentIndex>,
+        mut focus: ResMut<Focus>,
+        mut selection: ResMut<Selection>,
+    ) {
+        let Some(event::Event::Key(key_event)) = &event.0 else {
+            return;
+        };
+        let results = history
+            .0
+            .last()
+            .map(|exchange| &exchange.documents[..])
+            .unwrap_or_default();
+        if results.is_empty() {
+            *focus = Focus::Input;
+            return;
+        }
+        selection.result = selection.result.min(results.len() - 1);
+
+        if *focus == Focus::Results {
+            match key_event.code {
+                event::KeyCode::Up => selection.result = selection.result.saturating_sub(1),
+                event::KeyCode::Down => {
+                    selection.result = (selection.result + 1).min(results.len() - 1)
+                }
+                event::KeyCode::Enter => {
+                    let document = &results[selection.result];
+                    selection.preview = Some(Preview::new(
+                        &document.path,
+                        &document.kind,
+                        &document.krate,
+                        document.text.clone(),
+                        &index.0,
+                    ));
+                    selection.previous.clear();
+                    *focus = Focus::Preview;
+                }
+                event::KeyCode::Esc => *focus = Focus::Input,
+                _ => (),
+            }
+            return;
+        }
+
+        let selection = &mut *selection;
+        let Some(preview) = selection.preview.as_mut() else {
+            *focus = Focus::Results;
+            return;
+        };
+        match key_event.code {
+            event::KeyCode::Up => preview.scroll = preview.scroll.saturating_sub(1),
+            event::KeyCode::Down => {
+                preview.scroll = preview.scroll.saturating_add(1).min(preview.max_scroll)
+            }
+            event::KeyCode::PageUp => preview.scroll = preview.scroll.saturating_sub(10),
+            event::KeyCode::PageDown => {
+                preview.scroll = preview.scroll.saturating_add(10).min(preview.max_scroll)
+            }
+            event::KeyCode::Left => {
+                preview.selected_related = preview.selected_related.saturating_sub(1)
+            }
+            event::KeyCode::Right => {
+                preview.selected_related =
+                    (preview.selected_related + 1).min(preview.related.len().saturating_sub(1))
+            }
+            event::KeyCode::Enter => {
+                if let Some(related) = preview.related.get(preview.selected_related) {
+                    let related = Preview::open(related, &index.0);
+                    if let Some(previous) = selection.preview.replace(related) {
+                        selection.previous.push(previous);
+                    }
+                }
+            }
+            event::KeyCode::Backspace => {
+                if let Some(previous) = selection.previous.pop() {
+                    selection.preview = Some(previous);
+                }
+            }
+            event::KeyCode::Esc => {
+                selection.preview = None;
+                selection.previous.clear();
+                *focus = Focus::Results;
+            }
+            _ => (),
+        }
+    }
+
+    /// Renders the Markdown of a document: headings in bold, code blocks and inline code in
+    /// color.
+    pub fn markdown(text: &str) -> Vec<Line<'static>> {
+        let mut in_code_block = false;
+        text.lines()
+            .filter_map(|line| {
+                if line.trim_start().starts_with("```") {
+                    in_code_block = !in_code_block;
+                    return None;
+                }
+                if in_code_block {
+                    return Some(Line::from(format!("    {}", line)).yellow());
+                }
+                if let Some(heading) = line.strip_prefix('#') {
+                    return Some(
+                        Line::from(heading.trim_start_matches('#').trim().to_string()).bold(),
+                    );
+                }
+                let spans = line
+                    .split('`')
+                    .enumerate()
+                    .map(|(i, part)| {
+                        if i % 2 == 1 {
+                            part.to_string().cyan()
+                        } else {
+                            part.to_string().into()
+                        }
+                    })
+                    .collect::<Vec<Span>>();
+                Some(Line::from(spans))
+            })
+            .collect()
+    }
+
+    /// Retrieves documents for `prompt` and streams the answer into a new exchange.
+    #[allow(clippy::too_many_arguments)]
+    async fn ask(
+        ctx: &mut TaskContext,
+        prompt: String,
+        options: RetrieveOptions,
+        generate: bool,
+        ollama: SimpleOllama,
+        chroma: ChromaConfig,
+        db_name: String,
+        conversation: Conversation,
+    ) -> Result<(), Failure> {
+        let standalone_prompt = if generate {
+            conversation
+                .standalone_question(&ollama, &prompt)
+                .await
+                .map_err(|err| Failure::new("Prompt", &*err))?
+        } else {
+            None
+        };
+        let question = standalone_prompt.clone().unwrap_or(prompt.clone());
+        let documents = retrieve(&ollama, &chroma, &db_name, &question, &options)
+            .await
+            .map_err(|err| Failure::new("Prompt", &*err))?;
+
+        let exchange = Exchange {
+            prompt: prompt.clone(),
+            standalone_prompt: standalone_prompt.clone(),
+            documents: documents.clone(),
+            answer: None,
+            generating: generate,
+            cancelled: false,
+        };
+        let index = ctx
+            .run_on_main_thread(move |ctx| {
+                let world: &mut World = ctx.world;
+                if !generate {
+                    world.resource_mut::<Generation>().0 = None;
+                }
+                let mut history = world.resource_mut::<PromptsAndResponses>();
+                history.0.push(exchange);
+                history.0.len() - 1
+            })
+            .await;
+        if !generate {
+            return Ok(());
+        }
+
+        let mut tokens = answer_stream(&ollama, &question, &documents)
+            .await
+            .map_err(|err| Failure::new("Prompt", &*err))?;
+        let mut text = String::new();
+        while let Some(token) = tokens.next().await {
+            text.push_str(&token.map_err(|err| Failure::new("Prompt", &*err))?);
+            let answer = Answer {
+                text: text.clone(),
+                citations: vec![],
+            };
+            ctx.run_on_main_thread(move |ctx| {
+                let world: &mut World = ctx.world;
+                let mut history = world.resource_mut::<PromptsAndResponses>();
+                if let Some(exchange) = history.0.get_mut(index) {
+                    exchange.answer = Some(answer);
+                }
+            })
+            .await;
+        }
+
+        let answer = Answer::new(text, &documents);
+        ctx.run_on_main_thread(move |ctx| {
+            let world: &mut World = ctx.world;
+            world.resource_mut::<CurrentConversation>().0.push(
+                prompt,
+                standalone_prompt,
+                &answer,
+                documents,
+            );
+            let mut history = world.resource_mut::<PromptsAndResponses>();
+            if let Some(exchange) = history.0.get_mut(index) {
+                exchange.answer = Some(answer);
+                exchange.generating = false;
+            }
+            world.resource_mut::<Generation>().0 = None;
+        })
+        .await;
+        Ok(())
+    }
+
+    fn command(
+        input: &str,
+        history: &mut PromptsAndResponses,
+        conversation: &mut CurrentConversation,
+    ) -> String {
+        let (command, name) = input.split_once(' ').unwrap_or((input, ""));
+        let name = name.trim();
+        match command {
+            "/reset" => {
+                conversation.0 = Conversation::default();
+                history.0.clear();
+                "Conversation reset".to_string()
+            }
+            "/save" if !name.is_empty() => match conversation.0.save(name) {
+                Ok(()) => format!("Saved conversation {}", name),
+                Err(err) => format!("Couldn't save conversation {}: {}", name, err),
+            },
+            "/load" if !name.is_empty() => match Conversation::load(name) {
+                Ok(loaded) => {
+                    history.0 = loaded
+                        .turns
+                        .iter()
+                        .map(|turn| Exchange {
+                            prompt: turn.question.clone(),
+                            standalone_prompt: turn.standalone_question.clone(),
+                            documents: turn.documents.clone(),
+                            answer: Some(Answer {
+                                text: turn.answer.clone(),
+                                citations: turn.citations.clone(),
+                            }),
+                            generating: false,
+                            cancelled: false,
+                        })
+                        .collect();
+                    conversation.0 = loaded;
+                    format!("Loaded conversation {}", name)
+                }
+                Err(err) => format!("Couldn't load conversation {}: {}", name, err),
+            },
+            "/list" => match Conversation::list() {
+                Ok(names) if names.is_empty() => "No saved conversations".to_string(),
+                Ok(names) => format!("Saved conversations: {}", names.join(", ")),
+                Err(err) => format!("Couldn't list conversations: {}", err),
+            },
+            _ => "Commands: /reset, /save <name>, /load <name>, /list".to_string(),
+        }
+    }
+
+    fn cancel(generation: &mut Generation, history: &mut PromptsAndResponses) {
+        let Some(task) = generation.0.take() else {
+            return;
+        };
+        task.abort();
+        if let Some(exchange) = history.0.last_mut().filter(|exchange| exchange.generating) {
+            exchange.generating = false;
+            exchange.cancelled = true;
+        }
+    }
+
+    /// Greedy word wrap, as list items don't wrap.
+    fn wrap(text: &str, width: usize) -> Vec<String> {
+        let mut lines = vec![];
+        for paragraph in text.lines() {
+            let mut line = String::new();
+            for word in paragraph.split_whitespace() {
+                if !line.is_empty() && line.chars().count() + word.chars().count() + 1 > width {
+                    lines.push(std::mem::take(&mut line));
+                }
+                if !line.is_empty() {
+                    line.push(' ');
+                }
+                line.push_str(word);
+            }
+            lines.push(line);
+        }
+        lines
+    }
+
     fn render(
         mut drawer: WidgetDrawer,
         current_prompt: Res<CurrentPrompt>,
         history: Res<PromptsAndResponses>,
+        default_options: Res<DefaultRetrieveOptions>,
+        notice: Res<Notice>,
+        focus: Res<Focus>,
+        mut selection: ResMut<Selection>,
     ) {
         let frame = drawer.get_frame();
         let mut area = frame.area();
@@ -769,21 +1929,44 @@ mod prompt {
 
         let block = Block::bordered()
             .title(Line::from("Retrieve for prompt").bold().centered())
+            .title_bottom(
+                Line::from(vec![
+                    " Back to Menu ".into(),
+                    "<Esc> ".blue().bold(),
+                    " Cancel ".into(),
+                    "<Ctrl-C> ".blue().bold(),
+                    " Previous Prompts ".into(),
+                    "<Up> / <Down> ".blue().bold(),
+                    " Results ".into(),
+                    "<Tab> ".blue().bold(),
+                ])
+                .left_aligned(),
+            )
+            .title_bottom(Line::from(format!(" {} ", default_options.0).yellow()).right_aligned())
             .border_set(border::THICK);
 
         drawer.push_widget(Box::new(Clear), area, 2);
         drawer.push_widget(Box::new(block), area, 3);
 
-        let prompt_block = Block::bordered()
+        let mut prompt_block = Block::bordered()
             .title(Line::from("Prompt:").italic().green().left_aligned())
             .border_set(border::ROUNDED);
+        if let Some(notice) = &notice.0 {
+            prompt_block = prompt_block
+                .title_bottom(Line::from(format!(" {} ", notice).yellow()).right_aligned());
+        }
         let mut prompt_area = area.clone();
         prompt_area.y += 2;
         prompt_area.x += 1;
         prompt_area.height = 4;
         prompt_area.width -= 2;
+        let prompt = Line::from(current_prompt.0.spans());
         drawer.push_widget(
-            Box::new(Paragraph::new(Line::from(current_prompt.0.clone())).block(prompt_block)),
+            Box::new(
+                Paragraph::new(prompt)
+                    .wrap(Wrap { trim: false })
+                    .block(prompt_block),
+            ),
             prompt_area,
             4,
         );
@@ -793,89 +1976,1161 @@ mod prompt {
         prompt_area.x += 1;
         prompt_area.height -= 8;
         prompt_area.width -= 2;
-        let nb_lines = history.0.len() as i32 * 12;
-        drawer.push_widget(
-            Box::new(List::new(
-                history
-                    .0
-                    .iter()
-                    .flat_map(|(prompt, responses)| {
-                        std::iter::once(Text::from(vec![Line::from(vec![])]))
-                            .chain(std::iter::once(Text::from(vec![Line::from(vec![
-                                format!("> {}", prompt).into(),
-                            ])
+        if let Some(preview) = &mut selection.preview {
+            let mut preview_area = prompt_area;
+            prompt_area.width /= 2;
+            preview_area.x += prompt_area.width;
+            preview_area.width -= prompt_area.width;
+            render_preview(&mut drawer, preview, preview_area);
+        }
+        let width = (prompt_area.width as usize).saturating_sub(4);
+        let last = history.0.len().saturating_sub(1);
+        let lines = history
+            .0
+            .iter()
+            .enumerate()
+            .flat_map(|(i, exchange)| {
+                let mut lines = vec![
+                    Line::from(vec![]),
+                    Line::from(format!("> {}", exchange.prompt))
+                        .italic()
+                        .green(),
+                ];
+                if let Some(standalone_prompt) = &exchange.standalone_prompt {
+                    lines.push(
+                        Line::from(format!("  (as: {})", standalone_prompt))
                             .italic()
-                            .green()])))
-                            .chain(responses.iter().map(|(response, distance)| {
-                                Text::from(vec![Line::from(vec![format!(
-                                    "  - {:<30} ({:.2})",
-                                    response, distance
-                                )
-                                .into()])])
-                            }))
-                    })
-                    .skip((nb_lines - prompt_area.height as i32).max(0) as usize)
-                    .collect::<Vec<_>>(),
-            )),
+                            .dark_gray(),
+                    );
+                }
+                if let Some(answer) = &exchange.answer {
+                    lines.extend(
+                        wrap(&answer.text, width)
+                            .into_iter()
+                            .map(|line| Line::from(format!("  {}", line))),
+                    );
+                }
+                if exchange.generating {
+                    lines.push(Line::from("  Generating answer...").italic().dark_gray());
+                }
+                if exchange.cancelled {
+                    lines.push(Line::from("  Cancelled").italic().red());
+                }
+                if exchange.answer.is_some() || exchange.generating || exchange.cancelled {
+                    lines.push(Line::from(vec![]));
+                }
+                lines.extend(exchange.documents.iter().enumerate().map(|(j, document)| {
+                    let cited = exchange
+                        .answer
+                        .as_ref()
+                        .is_some_and(|answer| answer.citations.contains(&document.path));
+                    let selected = *focus != Focus::Input && i == last && j == selection.result;
+                    let line = Line::from(vec![
+                        format!("  {} {:<60}", if cited { "*" } else { "-" }, document.path).into(),
+                        format!(" {:<8}", document.kind).dark_gray(),
+                        match document.rerank_score {
+                            Some(rerank_score) => {
+                                format!(" ({:.2} -> {:.2})", document.similarity, rerank_score)
+                                    .into()
+                            }
+                            None => format!(" ({:.2})", document.similarity).into(),
+                        },
+                    ]);
+                    if selected {
+                        line.reversed()
+                    } else {
+                        line
+                    }
+                }));
+                lines
+            })
+            .collect::<Vec<_>>();
+        let skip = lines.len().saturating_sub(prompt_area.height as usize);
+        drawer.push_widget(
+            Box::new(List::new(lines.into_iter().skip(skip).collect::<Vec<_>>())),
             prompt_area,
             4,
         );
     }
-}
-
-mod unimplemented {
-
-    use ratatecs::prelude::*;
-    use ratatui::widgets::{Block, Paragraph};
-    use symbols::border;
-
-    use crate::CurrentAction;
-
-    pub fn panel<const A: u8>(app: &mut App) {
-        app.add_systems(Update, exit.run_if(in_state(CurrentAction::from_u8(A))));
-        app.add_systems(
-            PostUpdate,
-            render::<A>.run_if(in_state(CurrentAction::from_u8(A))),
-        );
-    }
-
-    fn exit(event: Res<BackendEvent>, mut next_state: ResMut<NextState<CurrentAction>>) {
-        if let Some(event) = &event.0 {
-            if let event::Event::Key(key_event) = event {
-                if key_event.code == event::KeyCode::Char(' ') {
-                    next_state.set(CurrentAction::Menu);
-                }
-            }
-        }
-    }
-
-    fn render<const A: u8>(mut drawer: WidgetDrawer) {
-        let frame = drawer.get_frame();
-        let mut area = frame.area();
-        area.x += 15;
-        area.y = (area.height / 2 - 10).max(10);
-        area.height = 8;
-        area.width -= 30;
-
-        let instructions = Line::from(vec![" Back to Menu ".into(), "<Space> ".blue().bold()]);
 
+    fn render_preview(drawer: &mut WidgetDrawer, preview: &mut Preview, area: Rect) {
         let block = Block::bordered()
             .title(
-                Line::from(format!("{:?}", CurrentAction::from_u8(A)))
+                Line::from(format!(" {} ", preview.path))
                     .bold()
-                    .centered(),
+                    .left_aligned(),
             )
-            .title_bottom(instructions.right_aligned())
-            .border_set(border::THICK);
+            .title_bottom(
+                Line::from(vec![
+                    " Scroll ".into(),
+                    "<Up> / <Down> ".blue().bold(),
+                    " Related ".into(),
+                    "<Left> / <Right> ".blue().bold(),
+                    " Open ".into(),
+                    "<Enter> ".blue().bold(),
+                    " Back ".into(),
+                    "<Backspace> ".blue().bold(),
+                    " Close ".into(),
+                    "<Esc> ".blue().bold(),
+                ])
+                .right_aligned(),
+            )
+            .border_set(border::ROUNDED);
 
+        let mut lines = vec![
+            Line::from(vec![
+                format!("{} ", preview.kind).dark_gray(),
+                "in crate ".dark_gray(),
+                preview.krate.clone().dark_gray(),
+            ]),
+            Line::from(vec![]),
+        ];
+        if !preview.related.is_empty() {
+            let mut related = vec!["Related: ".dark_gray()];
+            for (i, entry) in preview.related.iter().enumerate() {
+                let name = entry
+                    .path
+                    .rsplit("::")
+                    .next()
+                    .unwrap_or_default()
+                    .to_string();
+                related.push(if i == preview.selected_related {
+                    name.green().reversed()
+                } else {
+                    name.green()
+                });
+                related.push(" ".into());
+            }
+            lines.push(Line::from(related));
+            lines.push(Line::from(vec![]));
+        }
+        lines.extend(markdown(&preview.text));
+        // Inside the borders. Wrapping is estimated from the width of each line.
+        let width = area.width.saturating_sub(2).max(1) as usize;
+        let height = lines
+            .iter()
+            .map(|line| line.width().max(1).div_ceil(width))
+            .sum::<usize>();
+        preview.max_scroll = height
+            .saturating_sub(area.height.saturating_sub(2) as usize)
+            .try_into()
+            .unwrap_or(u16::MAX);
+
+        drawer.push_widget(Box::new(Clear), area, 4);
         drawer.push_widget(
             Box::new(
-                Paragraph::new(Line::from("unimplemented").italic())
-                    .centered()
+                Paragraph::new(lines)
+                    .wrap(Wrap { trim: false })
+                    .scroll((preview.scroll, 0))
                     .block(block),
             ),
             area,
-            1,
+            5,
         );
     }
 }
+
+mod start_service {
+    use std::{
+        error::Error,
+        future::Future,
+        marker::PhantomData,
+        time::{Duration, Instant},
+    };
+
+    use bevy_tokio_tasks::TokioTasksRuntime;
+    use doc_explorer::process::ManagedProcess;
+    use ratatecs::prelude::*;
+    use ratatui::widgets::{Block, Clear, List};
+    use symbols::border;
+
+    use crate::{Config, CurrentAction, Failure};
+
+    const STARTUP_TIMEOUT: Duration = Duration::from_secs(30);
+
+    /// A server that can be started from the menu.
+    pub trait Service: Send + Sync + 'static {
+        /// Name of the server, like "Chroma".
+        const NAME: &'static str;
+        /// Title of the panel.
+        const TITLE: &'static str;
+        /// Name of the start action, shown when it fails.
+        const START: &'static str;
+        /// The action showing the panel.
+        const ACTION: CurrentAction;
+
+        fn start(config: &doc_explorer::config::Config) -> Result<ManagedProcess, Box<dyn Error>>;
+
+        /// Where the server is expected to answer.
+        fn address(config: &doc_explorer::config::Config) -> String;
+
+        /// Whether the server answers.
+        fn is_up(config: &doc_explorer::config::Config) -> impl Future<Output = bool> + Send;
+    }
+
+    pub struct Chroma;
+
+    impl Service for Chroma {
+        const NAME: &'static str = "Chroma";
+        const TITLE: &'static str = "Vector Database";
+        const START: &'static str = "Start Vector Database";
+        const ACTION: CurrentAction = CurrentAction::StartDb;
+
+        fn start(config: &doc_explorer::config::Config) -> Result<ManagedProcess, Box<dyn Error>> {
+            config.chroma.start()
+        }
+
+        fn address(config: &doc_explorer::config::Config) -> String {
+            config.chroma.url.clone()
+        }
+
+        async fn is_up(config: &doc_explorer::config::Config) -> bool {
+            let Ok(client) = config.chroma.client() else {
+                return false;
+            };
+            client.heartbeat().await.is_ok()
+        }
+    }
+
+    pub struct Ollama;
+
+    impl Service for Ollama {
+        const NAME: &'static str = "Ollama";
+        const TITLE: &'static str = "Ollama";
+        const START: &'static str = "Start Ollama";
+        const ACTION: CurrentAction = CurrentAction::StartOllama;
+
+        fn start(config: &doc_explorer::config::Config) -> Result<ManagedProcess, Box<dyn Error>> {
+            config.ollama.start()
+        }
+
+        fn address(config: &doc_explorer::config::Config) -> String {
+            format!("{}:{}", config.ollama.host, config.ollama.port)
+        }
+
+        async fn is_up(config: &doc_explorer::config::Config) -> bool {
+            config.ollama.client().list_local_models().await.is_ok()
+        }
+    }
+
+    /// The server we started, stopped when the app exits.
+    #[derive(Resource)]
+    struct ServiceProcess<S: Service> {
+        process: ManagedProcess,
+        /// Where it was started to answer, with the config of the time.
+        address: String,
+        service: PhantomData<S>,
+    }
+
+    #[derive(Resource)]
+    enum Startup<S: Service> {
+        /// Waiting for the server to answer, numbered to tell apart the attempts.
+        Starting(u64, PhantomData<S>),
+        Ready,
+    }
+
+    pub fn panel<S: Service>(app: &mut App) {
+        app.add_systems(OnEnter(S::ACTION), start::<S>);
+        app.add_systems(Update, exit.run_if(in_state(S::ACTION)));
+        app.add_systems(Last, stop_on_exit::<S>);
+        app.add_systems(PostUpdate, render::<S>.run_if(in_state(S::ACTION)));
+    }
+
+    fn start<S: Service>(
+        mut commands: Commands,
+        config: Res<Config>,
+        runtime: ResMut<TokioTasksRuntime>,
+        process: Option<ResMut<ServiceProcess<S>>>,
+        mut attempts: Local<u64>,
+    ) {
+        let config = config.0.clone();
+        let address = S::address(&config);
+        let mut running = false;
+        if let Some(mut process) = process {
+            if process.process.exit_status().is_none() && process.address == address {
+                // Already running, only waits for it again and shows its logs.
+                running = true;
+            } else {
+                // Exited, or answering where the config no longer points.
+                process.process.stop();
+                commands.remove_resource::<ServiceProcess<S>>();
+            }
+        }
+        *attempts += 1;
+        let attempt = *attempts;
+        commands.insert_resource(Startup::<S>::Starting(attempt, PhantomData));
+        runtime.spawn_background_task(move |mut ctx| async move {
+            if !running {
+                let process = S::start(&config).map_err(|err| Failure::new(S::START, &*err));
+                let process = match process {
+                    Ok(process) => process,
+                    Err(failure) => {
+                        ctx.run_on_main_thread(move |ctx| {
+                            let world: &mut World = ctx.world;
+                            world.remove_resource::<Startup<S>>();
+                            failure.report(world);
+                        })
+                        .await;
+                        return;
+                    }
+                };
+                ctx.run_on_main_thread(move |ctx| {
+                    let world: &mut World = ctx.world;
+                    world.insert_resource(ServiceProcess::<S> {
+                        process,
+                        address,
+                        service: PhantomData,
+                    });
+                })
+                .await;
+            }
+
+            let started = Instant::now();
+            let result = loop {
+                if S::is_up(&config).await {
+                    break Ok(());
+                }
+                let exit_status = ctx
+                    .run_on_main_thread(|ctx| {
+                        let world: &mut World = ctx.world;
+                        world
+                            .get_resource_mut::<ServiceProcess<S>>()
+                            .and_then(|mut process| process.process.exit_status())
+                    })
+                    .await;
+                if let Some(exit_status) = exit_status {
+                    break Err(format!("{} exited: {}", S::NAME, exit_status));
+                }
+                if started.elapsed() > STARTUP_TIMEOUT {
+                    break Err(format!(
+                        "No answer from {} after {}s",
+                        S::address(&config),
+                        STARTUP_TIMEOUT.as_secs()
+                    ));
+                }
+                tokio::time::sleep(Duration::from_millis(500)).await;
+            };
+
+            ctx.run_on_main_thread(move |ctx| {
+                let world: &mut World = ctx.world;
+                // A later attempt took over the process.
+                if !matches!(
+                    world.get_resource::<Startup<S>>(),
+                    Some(Startup::Starting(current, _)) if *current == attempt
+                ) {
+                    return;
+                }
+                match result {
+                    Ok(()) => world.insert_resource(Startup::<S>::Ready),
+                    Err(err) => {
+                        world.remove_resource::<Startup<S>>();
+                        // Not left running, it would block the next attempt. The last lines of
+                        // output usually tell why it didn't start.
+                        let output = world
+                            .remove_resource::<ServiceProcess<S>>()
+                            .map(|mut process| {
+                                let logs = process.process.logs();
+                                process.process.stop();
+                                logs[logs.len().saturating_sub(3)..].to_vec()
+                            })
+                            .unwrap_or_default();
+                        Failure {
+                            action: S::START,
+                            chain: vec![err],
+                            output,
+                        }
+                        .report(world);
+                    }
+                }
+                world.resource_mut::<Config>().set_changed();
+            })
+            .await;
+        });
+    }
+
+    fn exit(event: Res<BackendEvent>, mut next_state: ResMut<NextState<CurrentAction>>) {
+        if let Some(event::Event::Key(key_event)) = &event.0 {
+            if key_event.code == event::KeyCode::Char(' ') {
+                next_state.set(CurrentAction::Menu);
+            }
+        }
+    }
+
+    fn stop_on_exit<S: Service>(
+        mut exit: EventReader<AppExit>,
+        process: Option<ResMut<ServiceProcess<S>>>,
+    ) {
+        if exit.read().next().is_none() {
+            return;
+        }
+        if let Some(mut process) = process {
+            process.process.stop();
+        }
+    }
+
+    fn render<S: Service>(
+        startup: Option<Res<Startup<S>>>,
+        process: Option<Res<ServiceProcess<S>>>,
+        mut drawer: WidgetDrawer,
+    ) {
+        let frame = drawer.get_frame();
+        let mut area = frame.area();
+        area.x += 5;
+        area.y += 5;
+        area.height -= 10;
+        area.width -= 10;
+
+        let status = match startup.as_deref() {
+            Some(Startup::Starting(..)) | None => {
+                Line::from(format!("Waiting for {}...", S::NAME)).italic()
+            }
+            Some(Startup::Ready) => Line::from("Running").green(),
+        };
+        let block = Block::bordered()
+            .title(Line::from(S::TITLE).bold().centered())
+            .title_bottom(status.left_aligned())
+            .title_bottom(
+                Line::from(vec![" Back to Menu ".into(), "<Space> ".blue().bold()]).right_aligned(),
+            )
+            .border_set(border::THICK);
+
+        let logs = process
+            .map(|process| process.process.logs())
+            .unwrap_or_default();
+        let skip = logs.len().saturating_sub(area.height as usize - 2);
+        let lines = logs
+            .into_iter()
+            .skip(skip)
+            .map(|line| Line::from(line).dark_gray())
+            .collect::<Vec<_>>();
+
+        drawer.push_widget(Box::new(Clear), area, 2);
+        drawer.push_widget(Box::new(List::new(lines).block(block)), area, 3);
+    }
+}
+
+mod failure {
+    use ratatecs::prelude::*;
+    use ratatui::widgets::{Block, Clear, Paragraph, Wrap};
+    use symbols::border;
+
+    use crate::{CurrentAction, Failure};
+
+    pub fn panel(app: &mut App) {
+        app.add_systems(Update, dismiss.run_if(in_state(CurrentAction::Failed)));
+        app.add_systems(PostUpdate, render.run_if(in_state(CurrentAction::Failed)));
+    }
+
+    fn dismiss(
+        event: Res<BackendEvent>,
+        mut commands: Commands,
+        mut next_state: ResMut<NextState<CurrentAction>>,
+    ) {
+        if let Some(event::Event::Key(key_event)) = &event.0 {
+            if key_event.code == event::KeyCode::Char(' ') {
+                commands.remove_resource::<Failure>();
+                next_state.set(CurrentAction::Menu);
+            }
+        }
+    }
+
+    fn render(failure: Option<Res<Failure>>, mut drawer: WidgetDrawer) {
+        let Some(failure) = failure else {
+            return;
+        };
+        let frame = drawer.get_frame();
+        let mut area = frame.area();
+        area.x += 15;
+        area.y = (area.height / 2 - 10).max(11);
+        area.height = if failure.output.is_empty() { 12 } else { 18 };
+        area.width -= 30;
+
+        let block = Block::bordered()
+            .title(
+                Line::from(format!(" {} failed ", failure.action))
+                    .bold()
+                    .red()
+                    .centered(),
+            )
+            .title_bottom(
+                Line::from(vec![" Back to Menu ".into(), "<Space> ".blue().bold()]).right_aligned(),
+            )
+            .border_set(border::THICK);
+
+        let mut lines = failure
+            .chain
+            .iter()
+            .enumerate()
+            .flat_map(|(i, err)| {
+                let line = if i == 0 {
+                    Line::from(format!(" {}", err))
+                } else {
+                    Line::from(format!(" caused by: {}", err)).dark_gray()
+                };
+                [line, Line::from(vec![])]
+            })
+            .collect::<Vec<_>>();
+        if !failure.output.is_empty() {
+            lines.push(Line::from(" output:").bold());
+            lines.extend(
+                failure
+                    .output
+                    .iter()
+                    .map(|line| Line::from(format!("   {}", line)).dark_gray()),
+            );
+        }
+
+        drawer.push_widget(Box::new(Clear), area, 5);
+        drawer.push_widget(
+            Box::new(
+                Paragraph::new(lines)
+                    .wrap(Wrap { trim: false })
+                    .block(block),
+            ),
+            area,
+            6,
+        );
+    }
+}
+
+mod browse {
+    use std::collections::HashSet;
+
+    use bevy_tokio_tasks::TokioTasksRuntime;
+    use doc_explorer::{browser::ItemTree, document::load_catalog, progress::Progress};
+    use ratatecs::prelude::*;
+    use ratatui::widgets::{Block, Clear, List, Paragraph, Wrap};
+    use symbols::border;
+
+    use crate::{prompt::markdown, Config, CurrentAction, Failure};
+
+    /// The items of the target, kept between visits of the panel.
+    #[derive(Resource)]
+    struct Items {
+        target: String,
+        tree: ItemTree,
+    }
+
+    #[derive(Resource, Default)]
+    struct Browser {
+        expanded: HashSet<usize>,
+        /// Index of the selected row.
+        selected: usize,
+        query: String,
+        /// Nodes matching `query`, best first.
+        results: Vec<usize>,
+        /// Scroll of the details of the selected node.
+        scroll: u16,
+        /// Highest scroll still showing the end of the details, set when they are rendered.
+        max_scroll: u16,
+    }
+
+    pub fn panel(app: &mut App) {
+        app.init_resource::<Browser>();
+        app.add_systems(OnEnter(CurrentAction::Browse), load);
+        app.add_systems(Update, input.run_if(in_state(CurrentAction::Browse)));
+        app.add_systems(PostUpdate, render.run_if(in_state(CurrentAction::Browse)));
+    }
+
+    fn load(
+        mut commands: Commands,
+        runtime: ResMut<TokioTasksRuntime>,
+        config: Res<Config>,
+        items: Option<Res<Items>>,
+    ) {
+        if items.is_some_and(|items| items.target == config.target) {
+            return;
+        }
+        commands.remove_resource::<Items>();
+        commands.insert_resource(Browser::default());
+        let target = config.target.clone();
+        runtime.spawn_background_task(|mut ctx| async move {
+            let loaded = target.clone();
+            // Reading the JSONs is blocking work.
+            let result = tokio::task::spawn_blocking(move || {
+                let catalog = load_catalog(target, &Progress::default())
+                    .map_err(|err| Failure::new("Browse Items", &*err))?;
+                Ok::<_, Failure>(ItemTree::build(&catalog))
+            })
+            .await
+            .unwrap_or_else(|err| Err(Failure::new("Browse Items", &err)));
+
+            ctx.run_on_main_thread(move |ctx| {
+                let world: &mut World = ctx.world;
+                match result {
+                    Ok(tree) => world.insert_resource(Items {
+                        target: loaded,
+                        tree,
+                    }),
+                    Err(failure)
+                        if *world.resource::<State<CurrentAction>>().get()
+                            == CurrentAction::Browse =>
+                    {
+                        failure.report(world)
+                    }
+                    Err(_) => {}
+                }
+            })
+            .await;
+        });
+    }
+
+    /// Visible nodes with their depth: the search results when searching, the expanded tree
+    /// otherwise.
+    fn rows(tree: &ItemTree, browser: &Browser) -> Vec<(usize, usize)> {
+        if !browser.query.is_empty() {
+            return browser.results.iter().map(|node| (*node, 0)).collect();
+        }
+        let mut rows = vec![];
+        let mut stack = tree
+            .roots
+            .iter()
+            .rev()
+            .map(|node| (*node, 0))
+            .collect::<Vec<_>>();
+        while let Some((node, depth)) = stack.pop() {
+            rows.push((node, depth));
+            if browser.expanded.contains(&node) {
+                stack.extend(
+                    tree.nodes[node]
+                        .children
+                        .iter()
+                        .rev()
+                        .map(|child| (*child, depth + 1)),
+                );
+            }
+        }
+        rows
+    }
+
+    /// Leaves the search and selects `node` in the tree.
+    fn reveal(tree: &ItemTree, browser: &mut Browser, node: usize) {
+        browser.query.clear();
+        browser.results.clear();
+        let mut parent = tree.nodes[node].parent;
+        while let Some(ancestor) = parent {
+            browser.expanded.insert(ancestor);
+            parent = tree.nodes[ancestor].parent;
+        }
+        browser.selected = rows(tree, browser)
+            .iter()
+            .position(|(row, _)| *row == node)
+            .unwrap_or_default();
+    }
+
+    fn input(
+        event: Res<BackendEvent>,
+        items: Option<Res<Items>>,
+        mut browser: ResMut<Browser>,
+        mut next_state: ResMut<NextState<CurrentAction>>,
+    ) {
+        let Some(event::Event::Key(key_event)) = &event.0 else {
+            return;
+        };
+        if key_event.code == event::KeyCode::Esc {
+            if browser.query.is_empty() {
+                next_state.set(CurrentAction::Menu);
+            } else {
+                browser.query.clear();
+                browser.results.clear();
+                browser.selected = 0;
+            }
+            return;
+        }
+        let Some(items) = items else {
+            return;
+        };
+        let tree = &items.tree;
+        let rows = rows(tree, &browser);
+        let selected = rows.get(browser.selected).map(|(node, _)| *node);
+        let searching = !browser.query.is_empty();
+        match key_event.code {
+            event::KeyCode::Up => {
+                browser.selected = browser.selected.saturating_sub(1);
+                browser.scroll = 0;
+            }
+            event::KeyCode::Down => {
+                browser.selected = (browser.selected + 1).min(rows.len().saturating_sub(1));
+                browser.scroll = 0;
+            }
+            event::KeyCode::PageUp => browser.scroll = browser.scroll.saturating_sub(5),
+            event::KeyCode::PageDown => {
+                browser.scroll = browser.scroll.saturating_add(5).min(browser.max_scroll)
+            }
+            event::KeyCode::Enter | event::KeyCode::Right if searching => {
+                if let Some(node) = selected {
+                    reveal(tree, &mut browser, node);
+                }
+            }
+            event::KeyCode::Right => {
+                if let Some(node) = selected {
+                    browser.expanded.insert(node);
+                }
+            }
+            event::KeyCode::Enter => {
+                if let Some(node) = selected {
+                    if !browser.expanded.remove(&node) {
+                        browser.expanded.insert(node);
+                    }
+                }
+            }
+            event::KeyCode::Left if !searching => {
+                if let Some(node) = selected {
+                    if !browser.expanded.remove(&node) {
+                        if let Some(parent) = tree.nodes[node].parent {
+                            reveal(tree, &mut browser, parent);
+                        }
+                    }
+                }
+            }
+            event::KeyCode::Backspace => {
+                browser.query.pop();
+                browser.results = tree.search(&browser.query);
+                browser.selected = 0;
+                browser.scroll = 0;
+            }
+            event::KeyCode::Char(c)
+                if !key_event.modifiers.contains(event::KeyModifiers::CONTROL) =>
+            {
+                browser.query.push(c);
+                browser.results = tree.search(&browser.query);
+                browser.selected = 0;
+                browser.scroll = 0;
+            }
+            _ => {}
+        }
+    }
+
+    fn render(
+        items: Option<Res<Items>>,
+        mut browser: ResMut<Browser>,
+        config: Res<Config>,
+        mut drawer: WidgetDrawer,
+    ) {
+        let frame = drawer.get_frame();
+        let mut area = frame.area();
+        area.x += 5;
+        area.y += 5;
+        area.height -= 10;
+        area.width -= 10;
+
+        let block = Block::bordered()
+            .title(
+                Line::from(format!(" Items of {} ", config.target))
+                    .bold()
+                    .centered(),
+            )
+            .title_bottom(
+                Line::from(vec![
+                    " Back ".into(),
+                    "<Esc> ".blue().bold(),
+                    " Expand / Collapse ".into(),
+                    "<Right> / <Left> ".blue().bold(),
+                    " Open ".into(),
+                    "<Enter> ".blue().bold(),
+                    " Scroll Details ".into(),
+                    "<PgUp> / <PgDn> ".blue().bold(),
+                ])
+                .left_aligned(),
+            )
+            .border_set(border::THICK);
+
+        drawer.push_widget(Box::new(Clear), area, 2);
+        drawer.push_widget(Box::new(block), area, 3);
+
+        let Some(items) = items else {
+            let mut loading_area = area;
+            loading_area.y += area.height / 2;
+            loading_area.height = 1;
+            drawer.push_widget(
+                Box::new(
+                    Paragraph::new(Line::from("Loading rustdoc JSONs...").italic()).centered(),
+                ),
+                loading_area,
+                4,
+            );
+            return;
+        };
+        let tree = &items.tree;
+
+        let mut search_area = area;
+        search_area.x += 2;
+        search_area.y += 1;
+        search_area.height = 1;
+        search_area.width = area.width / 2 - 2;
+        drawer.push_widget(
+            Box::new(Paragraph::new(Line::from(vec![
+                "Search: ".italic().green(),
+                browser.query.clone().into(),
+                " ".reversed(),
+            ]))),
+            search_area,
+            4,
+        );
+
+        let mut list_area = search_area;
+        list_area.y += 2;
+        list_area.height = area.height.saturating_sub(4);
+        let rows = rows(tree, &browser);
+        let skip = browser
+            .selected
+            .saturating_sub((list_area.height as usize).saturating_sub(1));
+        let lines = rows
+            .iter()
+            .enumerate()
+            .skip(skip)
+            .take(list_area.height as usize)
+            .map(|(i, (node, depth))| {
+                let item = &tree.nodes[*node];
+                let marker = if item.children.is_empty() {
+                    " "
+                } else if browser.expanded.contains(node) {
+                    "▾"
+                } else {
+                    "▸"
+                };
+                let name = if browser.query.is_empty() {
+                    item.name.clone()
+                } else {
+                    item.path.clone()
+                };
+                let name = if item.deprecated {
+                    name.crossed_out()
+                } else {
+                    name.into()
+                };
+                let line = Line::from(vec![
+                    format!("{}{} ", "  ".repeat(*depth), marker).into(),
+                    name,
+                    format!(" {}", item.kind).dark_gray(),
+                ]);
+                if i == browser.selected {
+                    line.reversed()
+                } else {
+                    line
+                }
+            })
+            .collect::<Vec<_>>();
+        drawer.push_widget(Box::new(List::new(lines)), list_area, 4);
+
+        let Some((node, _)) = rows.get(browser.selected) else {
+            return;
+        };
+        let item = &tree.nodes[*node];
+        let mut details_area = area;
+        details_area.x += area.width / 2;
+        details_area.y += 1;
+        details_area.width -= area.width / 2 + 1;
+        details_area.height -= 2;
+
+        let mut lines = vec![Line::from(item.kind).dark_gray()];
+        if item.deprecated {
+            lines.push(Line::from("Deprecated").red());
+        }
+        if !item.members.is_empty() {
+            lines.push(Line::from(vec![
+                "Members: ".dark_gray(),
+                item.members.join(", ").green(),
+            ]));
+        }
+        lines.push(Line::from(vec![]));
+        match &item.docs {
+            Some(docs) => lines.extend(markdown(docs)),
+            None => lines.push(Line::from("No documentation").italic().dark_gray()),
+        }
+        // Inside the borders. Wrapping is estimated from the width of each line.
+        let width = details_area.width.saturating_sub(2).max(1) as usize;
+        let height = lines
+            .iter()
+            .map(|line| line.width().max(1).div_ceil(width))
+            .sum::<usize>();
+        browser.max_scroll = height
+            .saturating_sub(details_area.height.saturating_sub(2) as usize)
+            .try_into()
+            .unwrap_or(u16::MAX);
+        drawer.push_widget(
+            Box::new(
+                Paragraph::new(lines)
+                    .wrap(Wrap { trim: false })
+                    .scroll((browser.scroll, 0))
+                    .block(
+                        Block::bordered()
+                            .title(Line::from(format!(" {} ", item.path)).bold().left_aligned())
+                            .border_set(border::ROUNDED),
+                    ),
+            ),
+            details_area,
+            4,
+        );
+    }
+}
+
+mod collections {
+    use std::error::Error;
+
+    use bevy_tokio_tasks::TokioTasksRuntime;
+    use doc_explorer::{
+        collections::{self, CollectionInfo},
+        config::PROJECT_CONFIG_PATH,
+    };
+    use ratatecs::prelude::*;
+    use ratatui::widgets::{Block, Clear, List, Paragraph};
+    use symbols::border;
+
+    use crate::{Config, CurrentAction, Failure};
+
+    #[derive(Resource, Default)]
+    struct Collections {
+        /// `None` while loading.
+        list: Option<Vec<CollectionInfo>>,
+        selected: usize,
+        /// Collection to delete once confirmed.
+        deleting: Option<String>,
+        notice: Option<String>,
+    }
+
+    pub fn panel(app: &mut App) {
+        app.add_systems(OnEnter(CurrentAction::Collections), load);
+        app.add_systems(Update, input.run_if(in_state(CurrentAction::Collections)));
+        app.add_systems(
+            PostUpdate,
+            render.run_if(in_state(CurrentAction::Collections)),
+        );
+    }
+
+    /// A change to a collection, made before listing them again.
+    enum Change {
+        Delete(String),
+        /// Assigns the collection to the active config.
+        Adopt(String),
+        /// Renames the collections named with the legacy hash.
+        Migrate,
+    }
+
+    fn load(mut commands: Commands, runtime: ResMut<TokioTasksRuntime>, config: Res<Config>) {
+        commands.insert_resource(Collections::default());
+        refresh(&runtime, config.0.clone(), None);
+    }
+
+    /// Lists the collections again, after making `change`.
+    fn refresh(
+        runtime: &TokioTasksRuntime,
+        config: doc_explorer::config::Config,
+        change: Option<Change>,
+    ) {
+        runtime.spawn_background_task(|mut ctx| async move {
+            let changed = change.is_some();
+            let result = async {
+                let notice = match &change {
+                    Some(Change::Delete(name)) => {
+                        collections::delete(&config.chroma, name).await?;
+                        None
+                    }
+                    Some(Change::Adopt(name)) => Some(format!(
+                        "Adopted {} as {}",
+                        name,
+                        collections::adopt(&config, name).await?
+                    )),
+                    Some(Change::Migrate) => {
+                        let renamed = collections::migrate(&config).await?;
+                        Some(if renamed.is_empty() {
+                            "Nothing to migrate".to_string()
+                        } else {
+                            format!("Renamed {} collections", renamed.len())
+                        })
+                    }
+                    None => None,
+                };
+                Ok::<_, Box<dyn Error>>((notice, collections::list(&config.chroma).await?))
+            }
+            .await
+            .map_err(|err| Failure::new("Manage Collections", &*err));
+
+            ctx.run_on_main_thread(move |ctx| {
+                let world: &mut World = ctx.world;
+                match result {
+                    Ok((notice, list)) => {
+                        let mut collections = world.resource_mut::<Collections>();
+                        collections.selected =
+                            collections.selected.min(list.len().saturating_sub(1));
+                        collections.list = Some(list);
+                        collections.notice = notice;
+                    }
+                    Err(failure) => failure.report(world),
+                }
+                if changed {
+                    world.resource_mut::<Config>().set_changed();
+                }
+            })
+            .await;
+        });
+    }
+
+    fn input(
+        event: Res<BackendEvent>,
+        runtime: ResMut<TokioTasksRuntime>,
+        mut collections: ResMut<Collections>,
+        mut config: ResMut<Config>,
+        mut next_state: ResMut<NextState<CurrentAction>>,
+    ) {
+        let Some(event::Event::Key(key_event)) = &event.0 else {
+            return;
+        };
+        let deleting = collections.deleting.take();
+        let Some(list) = &collections.list else {
+            if key_event.code == event::KeyCode::Char(' ') {
+                next_state.set(CurrentAction::Menu);
+            }
+            return;
+        };
+        let selected = list.get(collections.selected).cloned();
+        let count = list.len();
+        collections.notice = None;
+        match key_event.code {
+            event::KeyCode::Up => collections.selected = collections.selected.saturating_sub(1),
+            event::KeyCode::Down => {
+                collections.selected = (collections.selected + 1).min(count.saturating_sub(1))
+            }
+            event::KeyCode::Char(' ') => next_state.set(CurrentAction::Menu),
+            event::KeyCode::Char('d') => {
+                let Some(selected) = selected else {
+                    return;
+                };
+                if deleting.as_ref() == Some(&selected.name) {
+                    collections.list = None;
+                    refresh(
+                        &runtime,
+                        config.0.clone(),
+                        Some(Change::Delete(selected.name)),
+                    );
+                } else {
+                    collections.deleting = Some(selected.name);
+                }
+            }
+            event::KeyCode::Char('m') => {
+                collections.list = None;
+                refresh(&runtime, config.0.clone(), Some(Change::Migrate));
+            }
+            event::KeyCode::Char('a') => {
+                let Some(selected) = selected else {
+                    return;
+                };
+                collections.list = None;
+                refresh(
+                    &runtime,
+                    config.0.clone(),
+                    Some(Change::Adopt(selected.name)),
+                );
+            }
+            event::KeyCode::Enter => {
+                let Some(selected) = selected else {
+                    return;
+                };
+                match selected.apply(&config) {
+                    // Replacing the resource marks it changed, which refreshes the state.
+                    Ok(switched) => {
+                        config.0 = switched;
+                        match config.save() {
+                            Ok(()) => next_state.set(CurrentAction::Menu),
+                            Err(err) => {
+                                collections.notice =
+                                    Some(format!("Couldn't save {}: {}", PROJECT_CONFIG_PATH, err))
+                            }
+                        }
+                    }
+                    Err(err) => collections.notice = Some(err.to_string()),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn render(collections: Res<Collections>, config: Res<Config>, mut drawer: WidgetDrawer) {
+        let frame = drawer.get_frame();
+        let mut area = frame.area();
+        area.x += 5;
+        area.y += 5;
+        area.height -= 10;
+        area.width -= 10;
+
+        let mut block = Block::bordered()
+            .title(Line::from(" Collections ").bold().centered())
+            .title_bottom(
+                Line::from(vec![
+                    " Switch ".into(),
+                    "<Enter> ".blue().bold(),
+                    " Delete ".into(),
+                    "<d> ".blue().bold(),
+                    " Adopt ".into(),
+                    "<a> ".blue().bold(),
+                    " Migrate ".into(),
+                    "<m> ".blue().bold(),
+                    " Back to Menu ".into(),
+                    "<Space> ".blue().bold(),
+                ])
+                .left_aligned(),
+            )
+            .border_set(border::THICK);
+        if let Some(name) = &collections.deleting {
+            block = block.title_bottom(
+                Line::from(format!(" Press <d> again to delete {} ", name).yellow())
+                    .right_aligned(),
+            );
+        } else if let Some(notice) = &collections.notice {
+            block =
+                block.title_bottom(Line::from(format!(" {} ", notice).yellow()).right_aligned());
+        }
+
+        drawer.push_widget(Box::new(Clear), area, 2);
+        let Some(list) = &collections.list else {
+            drawer.push_widget(
+                Box::new(
+                    Paragraph::new(Line::from("Loading collections...").italic())
+                        .centered()
+                        .block(block),
+                ),
+                area,
+                3,
+            );
+            return;
+        };
+
+        let mut lines = vec![Line::from(format!(
+            "   {:<42} {:<16} {:<30} {:<8} {:>9} {:<14}",
+            "collection", "target", "model", "distance", "documents", "indexed"
+        ))
+        .bold()];
+        lines.extend(list.iter().enumerate().map(|(i, collection)| {
+            let line = Line::from(vec![
+                if collection.is_active(&config) {
+                    " * ".green()
+                } else {
+                    "   ".into()
+                },
+                format!(
+                    "{:<42} {:<16} {:<30} {:<8} {:>9} {:<14}",
+                    collections::elide(&collection.name, 42),
+                    collection.target.as_deref().unwrap_or("?"),
+                    collection.embedding_model.as_deref().unwrap_or("?"),
+                    collection
+                        .distance
+                        .map_or("?", |distance| distance.as_space()),
+                    collection.documents,
+                    collection
+                        .indexed_at
+                        .map_or("?".to_string(), collections::ago)
+                )
+                .into(),
+            ]);
+            if i == collections.selected {
+                line.reversed()
+            } else {
+                line
+            }
+        }));
+        if list.is_empty() {
+            lines.push(Line::from("   No collections").italic().dark_gray());
+        }
+        drawer.push_widget(Box::new(List::new(lines).block(block)), area, 3);
+    }
+}
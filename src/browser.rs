@@ -0,0 +1,188 @@
+use std::collections::HashSet;
+
+use rustdoc_types::{Crate, Id, ItemEnum, StructKind};
+
+use crate::document::CrateCatalog;
+
+/// How many search results are kept.
+const MAX_RESULTS: usize = 200;
+
+/// The crates of a catalog as a tree of modules and items, to browse their documentation.
+#[derive(Debug, Default)]
+pub struct ItemTree {
+    pub nodes: Vec<ItemNode>,
+    /// The root module of each crate.
+    pub roots: Vec<usize>,
+}
+
+#[derive(Debug)]
+pub struct ItemNode {
+    pub name: String,
+    pub path: String,
+    pub kind: &'static str,
+    pub docs: Option<String>,
+    pub deprecated: bool,
+    /// Fields, variants or trait items.
+    pub members: Vec<String>,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+}
+
+impl ItemTree {
+    pub fn build(catalog: &CrateCatalog) -> Self {
+        let mut tree = ItemTree::default();
+        for (name, krate) in catalog.iter().flatten() {
+            let Some(root) = krate.index.get(&krate.root) else {
+                continue;
+            };
+            let node = tree.push(None, name.clone(), name.clone(), "crate", root.docs.clone());
+            tree.roots.push(node);
+            let mut visited = HashSet::new();
+            tree.explore_module(krate, krate.root, node, &mut visited);
+        }
+        tree
+    }
+
+    fn push(
+        &mut self,
+        parent: Option<usize>,
+        name: String,
+        path: String,
+        kind: &'static str,
+        docs: Option<String>,
+    ) -> usize {
+        let node = self.nodes.len();
+        self.nodes.push(ItemNode {
+            name,
+            path,
+            kind,
+            docs,
+            deprecated: false,
+            members: vec![],
+            parent,
+            children: vec![],
+        });
+        if let Some(parent) = parent {
+            self.nodes[parent].children.push(node);
+        }
+        node
+    }
+
+    fn explore_module(&mut self, krate: &Crate, id: Id, node: usize, visited: &mut HashSet<Id>) {
+        if !visited.insert(id) {
+            return;
+        }
+        let Some(ItemEnum::Module(module)) = krate.index.get(&id).map(|item| &item.inner) else {
+            return;
+        };
+        for child in &module.items {
+            let Some(item) = krate.index.get(child) else {
+                continue;
+            };
+            // Re-exports of items of the same crate are shown where they are re-exported, the
+            // others point to the crate they come from.
+            let (item, name) = match &item.inner {
+                ItemEnum::Use(used) if used.is_glob => continue,
+                ItemEnum::Use(used) => match used.id.and_then(|id| krate.index.get(&id)) {
+                    Some(target) => (target, used.name.clone()),
+                    None => {
+                        let path = format!("{}::{}", self.nodes[node].path, used.name);
+                        let docs = Some(format!("Re-export of `{}`.", used.source));
+                        self.push(Some(node), used.name.clone(), path, "re-export", docs);
+                        continue;
+                    }
+                },
+                _ => match &item.name {
+                    Some(name) => (item, name.clone()),
+                    None => continue,
+                },
+            };
+            let Some(kind) = kind(&item.inner) else {
+                continue;
+            };
+            let path = format!("{}::{}", self.nodes[node].path, name);
+            let child_node = self.push(Some(node), name, path, kind, item.docs.clone());
+            self.nodes[child_node].deprecated = item.deprecation.is_some();
+            self.nodes[child_node].members = members(krate, &item.inner);
+            if kind == "module" {
+                self.explore_module(krate, item.id, child_node, visited);
+            }
+        }
+        let mut children = std::mem::take(&mut self.nodes[node].children);
+        children.sort_by(|a, b| self.nodes[*a].name.cmp(&self.nodes[*b].name));
+        self.nodes[node].children = children;
+    }
+
+    /// Nodes whose path fuzzy matches `query`, best first.
+    pub fn search(&self, query: &str) -> Vec<usize> {
+        let mut results = self
+            .nodes
+            .iter()
+            .enumerate()
+            .filter_map(|(i, node)| Some((i, fuzzy_score(query, &node.path)?)))
+            .collect::<Vec<_>>();
+        results.sort_by(|a, b| {
+            b.1.cmp(&a.1)
+                .then(self.nodes[a.0].path.len().cmp(&self.nodes[b.0].path.len()))
+        });
+        results.truncate(MAX_RESULTS);
+        results.into_iter().map(|(i, _)| i).collect()
+    }
+}
+
+fn kind(item: &ItemEnum) -> Option<&'static str> {
+    Some(match item {
+        ItemEnum::Module(_) => "module",
+        ItemEnum::Struct(_) => "struct",
+        ItemEnum::Enum(_) => "enum",
+        ItemEnum::Union(_) => "union",
+        ItemEnum::Trait(_) => "trait",
+        ItemEnum::Function(_) => "function",
+        ItemEnum::TypeAlias(_) => "type",
+        ItemEnum::Constant { .. } => "constant",
+        ItemEnum::Static(_) => "static",
+        ItemEnum::Macro(_) | ItemEnum::ProcMacro(_) => "macro",
+        _ => return None,
+    })
+}
+
+fn members(krate: &Crate, item: &ItemEnum) -> Vec<String> {
+    let ids = match item {
+        ItemEnum::Struct(stru) => match &stru.kind {
+            StructKind::Plain { fields, .. } => &fields[..],
+            _ => &[][..],
+        },
+        ItemEnum::Enum(enumeration) => &enumeration.variants[..],
+        ItemEnum::Trait(tra) => &tra.items[..],
+        _ => &[][..],
+    };
+    ids.iter()
+        .filter_map(|id| krate.index.get(id)?.name.clone())
+        .collect()
+}
+
+/// Scores `candidate` when all the chars of `query` appear in it in order, ignoring case.
+/// Consecutive chars and chars starting a word score higher.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i32> {
+    let mut score = 0;
+    let mut previous_match = None;
+    let mut candidate_chars = candidate.char_indices();
+    for query_char in query.chars().filter(|c| !c.is_whitespace()) {
+        let query_char = query_char.to_ascii_lowercase();
+        let (i, _) = candidate_chars
+            .by_ref()
+            .find(|(_, c)| c.to_ascii_lowercase() == query_char)?;
+        score += 1;
+        if previous_match.is_some_and(|previous| previous + 1 == i) {
+            score += 2;
+        }
+        let word_start = i == 0
+            || matches!(candidate.as_bytes()[i - 1], b':' | b'_')
+            || candidate[i..].starts_with(char::is_uppercase);
+        if word_start {
+            score += 3;
+        }
+        previous_match = Some(i);
+    }
+    Some(score)
+}
@@ -0,0 +1,103 @@
+use std::{error::Error, fmt};
+
+use chromadb::v2::{
+    client::{ChromaAuthMethod, ChromaClientOptions, ChromaTokenHeader},
+    ChromaClient,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::process::ManagedProcess;
+
+#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChromaConfig {
+    pub url: String,
+    /// Read from the config files or `CHROMA_AUTH_TOKEN`, but never written back so that it
+    /// doesn't end up in a committed project config.
+    #[serde(skip_serializing)]
+    pub auth_token: Option<String>,
+    /// The chromadb client always addresses the default tenant, any other value is rejected
+    /// instead of silently writing to the wrong place.
+    pub tenant: String,
+    pub database: String,
+    /// Chroma CLI used to start a local server.
+    pub command: String,
+    /// Where a local server stores its data.
+    pub persist_dir: String,
+}
+
+impl ChromaConfig {
+    pub fn client(&self) -> Result<ChromaClient, Box<dyn Error>> {
+        self.validate()?;
+        let auth = match &self.auth_token {
+            Some(token) => ChromaAuthMethod::TokenAuth {
+                token: token.clone(),
+                header: ChromaTokenHeader::Authorization,
+            },
+            None => ChromaAuthMethod::None,
+        };
+        Ok(ChromaClient::new(ChromaClientOptions {
+            url: self.url.clone(),
+            auth,
+            database: self.database.clone(),
+        }))
+    }
+
+    /// Checks that [`Self::tenant`] is the default one, the only one the client can address.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.tenant != "default_tenant" {
+            return Err(format!(
+                "Unsupported Chroma tenant {}, only default_tenant can be used",
+                self.tenant
+            ));
+        }
+        Ok(())
+    }
+
+    /// Starts a local Chroma server listening on the port of [`Self::url`].
+    pub fn start(&self) -> Result<ManagedProcess, Box<dyn Error>> {
+        let port = self
+            .url
+            .trim_end_matches('/')
+            .rsplit_once(':')
+            .and_then(|(_, port)| port.parse::<u16>().ok())
+            .unwrap_or(8000);
+        ManagedProcess::spawn(
+            &self.command,
+            &[
+                "run".to_string(),
+                "--path".to_string(),
+                self.persist_dir.clone(),
+                "--port".to_string(),
+                port.to_string(),
+            ],
+            &[],
+        )
+    }
+}
+
+impl fmt::Debug for ChromaConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChromaConfig")
+            .field("url", &self.url)
+            .field("auth_token", &self.auth_token.as_ref().map(|_| "***"))
+            .field("tenant", &self.tenant)
+            .field("database", &self.database)
+            .field("command", &self.command)
+            .field("persist_dir", &self.persist_dir)
+            .finish()
+    }
+}
+
+impl Default for ChromaConfig {
+    fn default() -> Self {
+        Self {
+            url: std::env::var("CHROMA_URL").unwrap_or("http://localhost:8000".to_string()),
+            auth_token: std::env::var("CHROMA_AUTH_TOKEN").ok(),
+            tenant: std::env::var("CHROMA_TENANT").unwrap_or("default_tenant".to_string()),
+            database: std::env::var("CHROMA_DATABASE").unwrap_or("default_database".to_string()),
+            command: "chroma".to_string(),
+            persist_dir: "chroma-data".to_string(),
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use std::{
+    collections::HashSet,
+    error::Error,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use chromadb::v2::collection::ChromaCollection;
+use serde_json::{Map, Value};
+
+use crate::{
+    chroma::ChromaConfig,
+    config::{Config, Distance, Renderer},
+};
+
+/// Metadata key of the last indexing time of a collection, in seconds since the Unix epoch.
+pub const INDEXED_AT_KEY: &str = "indexed_at";
+
+/// A Chroma collection with the config it was indexed with, when it was recorded.
+#[derive(Debug, Clone)]
+pub struct CollectionInfo {
+    pub name: String,
+    pub target: Option<String>,
+    pub embedding_model: Option<String>,
+    pub distance: Option<Distance>,
+    /// Collections indexed before the renderer was recorded used the default one.
+    pub renderer: Renderer,
+    pub documents: usize,
+    pub indexed_at: Option<SystemTime>,
+}
+
+impl CollectionInfo {
+    pub fn is_active(&self, config: &Config) -> bool {
+        self.name == config.as_db_name()
+    }
+
+    /// `config` set to use this collection.
+    pub fn apply(&self, config: &Config) -> Result<Config, Box<dyn Error>> {
+        let (Some(target), Some(embedding_model), Some(distance)) =
+            (&self.target, &self.embedding_model, self.distance)
+        else {
+            return Err(format!("Collection {} has no recorded config", self.name).into());
+        };
+        Ok(Config {
+            target: target.clone(),
+            embedding_model: embedding_model.clone(),
+            distance,
+            renderer: self.renderer,
+            ..config.clone()
+        })
+    }
+}
+
+/// Metadata of the collection of `config`, recording what it was indexed with. Chroma doesn't
+/// let `hnsw:space` be modified, so the distance is recorded under its own key.
+pub fn metadata(config: &Config) -> Map<String, Value> {
+    let mut metadata = Map::new();
+    metadata.insert("distance".to_string(), config.distance.as_space().into());
+    metadata.insert("target".to_string(), config.target.clone().into());
+    metadata.insert(
+        "embedding_model".to_string(),
+        config.embedding_model.clone().into(),
+    );
+    metadata.insert("renderer".to_string(), config.renderer.as_str().into());
+    metadata
+}
+
+/// [`metadata`] to create the collection of `config` with, setting its distance function.
+pub fn creation_metadata(config: &Config) -> Map<String, Value> {
+    let mut metadata = metadata(config);
+    metadata.insert("hnsw:space".to_string(), config.distance.as_space().into());
+    metadata
+}
+
+/// The distance function of `collection`, as a Chroma `hnsw:space`.
+pub fn space(collection: &ChromaCollection) -> Option<&str> {
+    let metadata = collection.metadata()?;
+    metadata
+        .get("distance")
+        .or_else(|| metadata.get("hnsw:space"))
+        .and_then(Value::as_str)
+}
+
+/// When `collection` was last indexed.
+pub fn indexed_at(collection: &ChromaCollection) -> Option<SystemTime> {
+    let seconds = collection.metadata()?.get(INDEXED_AT_KEY)?.as_u64()?;
+    Some(UNIX_EPOCH + Duration::from_secs(seconds))
+}
+
+/// `name` shortened to `width` characters by eliding its middle, which keeps the hash suffix of
+/// [`Config::as_db_name`] that tells apart similar names.
+pub fn elide(name: &str, width: usize) -> String {
+    let chars = name.chars().collect::<Vec<_>>();
+    if chars.len() <= width {
+        return name.to_string();
+    }
+    let tail = 9.min(width.saturating_sub(1));
+    let head = width.saturating_sub(tail + 1);
+    chars[..head]
+        .iter()
+        .chain(['…'].iter())
+        .chain(&chars[chars.len() - tail..])
+        .collect()
+}
+
+/// How long ago `time` was, like `5 min ago`.
+pub fn ago(time: SystemTime) -> String {
+    let minutes = time.elapsed().map_or(0, |elapsed| elapsed.as_secs() / 60);
+    match minutes {
+        0 => "just now".to_string(),
+        1..60 => format!("{} min ago", minutes),
+        60..1440 => format!("{} h ago", minutes / 60),
+        _ => format!("{} days ago", minutes / 1440),
+    }
+}
+
+/// The collections of the Chroma database, sorted by name.
+pub async fn list(chroma: &ChromaConfig) -> Result<Vec<CollectionInfo>, Box<dyn Error>> {
+    let chroma = chroma.client()?;
+    let mut collections = vec![];
+    for collection in chroma.list_collections().await? {
+        let documents = collection.count().await?;
+        collections.push(describe(&collection, documents));
+    }
+    collections.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(collections)
+}
+
+fn describe(collection: &ChromaCollection, documents: usize) -> CollectionInfo {
+    let metadata = collection.metadata();
+    let field = |key: &str| {
+        metadata
+            .and_then(|metadata| metadata.get(key))
+            .and_then(Value::as_str)
+            .map(str::to_string)
+    };
+    CollectionInfo {
+        name: collection.name().to_string(),
+        target: field("target"),
+        embedding_model: field("embedding_model"),
+        distance: space(collection).and_then(|space| space.parse().ok()),
+        renderer: field("renderer")
+            .and_then(|renderer| renderer.parse().ok())
+            .unwrap_or_default(),
+        documents,
+        indexed_at: indexed_at(collection),
+    }
+}
+
+/// Renames the collections still named by [`Config::legacy_db_name`] to their
+/// [`Config::as_db_name`]: the one of `config`, and the ones whose config is recorded in their
+/// metadata. As the legacy name depends on the Rust version, this is best effort, see [`adopt`].
+/// Returns the renamed collections as `(old, new)`.
+pub async fn migrate(config: &Config) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    let chroma = config.chroma.client()?;
+    let collections = chroma.list_collections().await?;
+    let names = collections
+        .iter()
+        .map(|collection| collection.name().to_string())
+        .collect::<HashSet<_>>();
+
+    let mut renamed = vec![];
+    for collection in &collections {
+        let name = collection.name();
+        let owner = if name == config.legacy_db_name() {
+            Some(config.clone())
+        } else {
+            describe(collection, 0)
+                .apply(config)
+                .ok()
+                .filter(|owner| owner.legacy_db_name() == name)
+        };
+        let Some(owner) = owner else {
+            continue;
+        };
+        let new_name = owner.as_db_name();
+        // Already indexed again under the new name, the old collection is left to be deleted.
+        if names.contains(&new_name) {
+            continue;
+        }
+        assign(collection, &owner).await?;
+        renamed.push((name.to_string(), new_name));
+    }
+    Ok(renamed)
+}
+
+/// Assigns the collection `name` to `config`, for collections indexed with it under another
+/// name, like the ones [`migrate`] can't recognize. Returns the new name of the collection.
+pub async fn adopt(config: &Config, name: &str) -> Result<String, Box<dyn Error>> {
+    let chroma = config.chroma.client()?;
+    let new_name = config.as_db_name();
+    if name != new_name
+        && chroma
+            .list_collections()
+            .await?
+            .iter()
+            .any(|collection| collection.name() == new_name)
+    {
+        return Err(format!("Collection {} already exists", new_name).into());
+    }
+    let collection = chroma.get_collection(name).await?;
+    if let Some(space) = space(&collection).filter(|space| *space != config.distance.as_space()) {
+        return Err(format!(
+            "Collection {} uses the {} distance, not {}",
+            name,
+            space,
+            config.distance.as_space()
+        )
+        .into());
+    }
+    assign(&collection, config).await?;
+    Ok(new_name)
+}
+
+/// Renames `collection` to the one of `owner`, recording `owner` in its metadata.
+async fn assign(collection: &ChromaCollection, owner: &Config) -> Result<(), Box<dyn Error>> {
+    let new_name = owner.as_db_name();
+    let mut metadata = metadata(owner);
+    if let Some(indexed_at) = collection
+        .metadata()
+        .and_then(|metadata| metadata.get(INDEXED_AT_KEY))
+    {
+        metadata.insert(INDEXED_AT_KEY.to_string(), indexed_at.clone());
+    }
+    let rename = (collection.name() != new_name).then_some(new_name.as_str());
+    collection.modify(rename, Some(&metadata)).await?;
+    Ok(())
+}
+
+pub async fn delete(chroma: &ChromaConfig, name: &str) -> Result<(), Box<dyn Error>> {
+    chroma.client()?.delete_collection(name).await?;
+    Ok(())
+}
@@ -0,0 +1,346 @@
+use std::{
+    error::Error,
+    hash::{DefaultHasher, Hash, Hasher},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{chroma::ChromaConfig, ollama::OllamaConfig};
+
+/// Config of the current project, taking precedence over the user config.
+pub const PROJECT_CONFIG_PATH: &str = "doc-explorer.toml";
+
+/// Chroma rejects collection names longer than this.
+const MAX_DB_NAME_LEN: usize = 63;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Distance {
+    #[serde(rename = "l2")]
+    SquaredL2,
+    #[serde(rename = "ip")]
+    InnerProduct,
+    #[serde(rename = "cosine")]
+    Cosine,
+}
+
+impl Distance {
+    pub const ALL: [Distance; 3] = [
+        Distance::SquaredL2,
+        Distance::InnerProduct,
+        Distance::Cosine,
+    ];
+
+    /// The matching `hnsw:space` of a Chroma collection.
+    pub fn as_space(&self) -> &'static str {
+        match self {
+            Distance::SquaredL2 => "l2",
+            Distance::InnerProduct => "ip",
+            Distance::Cosine => "cosine",
+        }
+    }
+}
+
+impl FromStr for Distance {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "l2" => Ok(Distance::SquaredL2),
+            "ip" => Ok(Distance::InnerProduct),
+            "cosine" => Ok(Distance::Cosine),
+            _ => Err(format!("Unknown distance {}", s)),
+        }
+    }
+}
+
+/// How the generated documents are written. Changing it needs the documents generated and
+/// embedded again.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Renderer {
+    /// Sentences, like "Transform is a struct." followed by its docs and members.
+    #[default]
+    Prose,
+    /// A heading with the item path, its docs, then a section per member.
+    Markdown,
+}
+
+impl Renderer {
+    pub const ALL: [Renderer; 2] = [Renderer::Prose, Renderer::Markdown];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Renderer::Prose => "prose",
+            Renderer::Markdown => "markdown",
+        }
+    }
+}
+
+impl FromStr for Renderer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "prose" => Ok(Renderer::Prose),
+            "markdown" => Ok(Renderer::Markdown),
+            _ => Err(format!("Unknown renderer {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub target: String,
+    pub embedding_model: String,
+    pub chat_model: String,
+    pub distance: Distance,
+    pub renderer: Renderer,
+    pub chroma: ChromaConfig,
+    pub ollama: OllamaConfig,
+}
+
+impl Config {
+    /// Reads the user config then the project config, each value set in a file overriding the
+    /// ones before it. Missing files are skipped.
+    pub fn load() -> Result<Self, Box<dyn Error>> {
+        let mut table = toml::Table::new();
+        for path in user_config_path()
+            .into_iter()
+            .chain([PathBuf::from(PROJECT_CONFIG_PATH)])
+        {
+            if let Some(file) = read_table(&path)? {
+                merge(&mut table, file);
+            }
+        }
+        let config: Config = toml::Value::Table(table).try_into()?;
+        config.ollama.validate()?;
+        config.chroma.validate()?;
+        Ok(config)
+    }
+
+    /// Writes to the project config the values that differ from the defaults and the user
+    /// config, so that the others keep following them. Values the project config already sets
+    /// stay in it.
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        let mut inherited = toml::Table::try_from(Config::default())?;
+        if let Some(user) = user_config_path()
+            .map(|path| read_table(&path))
+            .transpose()?
+            .flatten()
+        {
+            merge(&mut inherited, user);
+        }
+        let mut project = read_table(Path::new(PROJECT_CONFIG_PATH))?.unwrap_or_default();
+        // Never serialized, so kept as the user wrote it.
+        let auth_token = project
+            .get("chroma")
+            .and_then(|chroma| chroma.get("auth_token"))
+            .cloned();
+        update(&mut project, &inherited, toml::Table::try_from(self)?);
+        if let Some(auth_token) = auth_token {
+            if let toml::Value::Table(chroma) = project
+                .entry("chroma")
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+            {
+                chroma.insert("auth_token".to_string(), auth_token);
+            }
+        }
+        std::fs::write(PROJECT_CONFIG_PATH, toml::to_string_pretty(&project)?)?;
+        Ok(())
+    }
+
+    /// Name of the collection indexed with this config, like
+    /// `bevy-nomic-embed-text-latest-l2-e2e5c328`. The suffix tells apart configs that
+    /// sanitize to the same name. The renderer is only part of the name when it isn't the
+    /// default one, so that collections indexed before it existed keep their name.
+    pub fn as_db_name(&self) -> String {
+        let mut parts = vec![
+            self.target.as_str(),
+            self.embedding_model.as_str(),
+            self.distance.as_space(),
+        ];
+        if self.renderer != Renderer::default() {
+            parts.push(self.renderer.as_str());
+        }
+        let suffix = format!("-{:08x}", fnv1a(parts.join("\0").as_bytes()) as u32);
+
+        let mut name = String::new();
+        for c in parts.join("-").chars() {
+            let c = if c.is_ascii_alphanumeric() || c == '.' || c == '_' {
+                c
+            } else {
+                '-'
+            };
+            // Chroma rejects consecutive periods, and repeated dashes are noise.
+            if !((c == '-' || c == '.') && name.ends_with(c)) {
+                name.push(c);
+            }
+        }
+        name.truncate(MAX_DB_NAME_LEN - suffix.len());
+        let name = name.trim_matches(|c: char| !c.is_ascii_alphanumeric());
+        // Chroma names must start with a letter or a digit.
+        let name = if name.is_empty() { "collection" } else { name };
+        format!("{}{}", name, suffix)
+    }
+
+    /// Name of the collection of this config before [`Self::as_db_name`] was made stable. It
+    /// depends on [`DefaultHasher`], which can change between Rust versions.
+    pub fn legacy_db_name(&self) -> String {
+        let mut hash = DefaultHasher::new();
+        (&self.target, &self.embedding_model, &self.distance).hash(&mut hash);
+        hash.finish().to_string()
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            target: "bevy".to_string(),
+            embedding_model: "nomic-embed-text:latest".to_string(),
+            chat_model: "llama3.2:latest".to_string(),
+            distance: Distance::SquaredL2,
+            renderer: Renderer::default(),
+            chroma: ChromaConfig::default(),
+            ollama: OllamaConfig::default(),
+        }
+    }
+}
+
+/// `$XDG_CONFIG_HOME/doc-explorer/config.toml`, or under `~/.config` when it isn't set.
+pub fn user_config_path() -> Option<PathBuf> {
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_home.join("doc-explorer").join("config.toml"))
+}
+
+/// The content of the config file at `path`, `None` when it doesn't exist.
+fn read_table(path: &Path) -> Result<Option<toml::Table>, Box<dyn Error>> {
+    let Ok(content) = std::fs::read_to_string(path) else {
+        return Ok(None);
+    };
+    let table = content
+        .parse::<toml::Table>()
+        .map_err(|err| format!("Invalid config {}: {}", path.display(), err))?;
+    Ok(Some(table))
+}
+
+fn merge(table: &mut toml::Table, overrides: toml::Table) {
+    for (key, value) in overrides {
+        match (table.get_mut(&key), value) {
+            (Some(toml::Value::Table(table)), toml::Value::Table(overrides)) => {
+                merge(table, overrides)
+            }
+            (_, value) => {
+                table.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Sets in `project` the values of `current` that differ from `inherited` or that `project`
+/// already sets, and removes the ones `current` doesn't have.
+fn update(project: &mut toml::Table, inherited: &toml::Table, current: toml::Table) {
+    project.retain(|key, _| current.contains_key(key));
+    for (key, value) in current {
+        match value {
+            toml::Value::Table(current) => {
+                let empty = toml::Table::new();
+                let inherited = match inherited.get(&key) {
+                    Some(toml::Value::Table(inherited)) => inherited,
+                    _ => &empty,
+                };
+                let mut table = match project.remove(&key) {
+                    Some(toml::Value::Table(table)) => table,
+                    _ => toml::Table::new(),
+                };
+                update(&mut table, inherited, current);
+                if !table.is_empty() {
+                    project.insert(key, toml::Value::Table(table));
+                }
+            }
+            value => {
+                if project.contains_key(&key) || inherited.get(&key) != Some(&value) {
+                    project.insert(key, value);
+                }
+            }
+        }
+    }
+}
+
+/// 64-bit FNV-1a, a hash that doesn't change between builds.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(toml: &str) -> toml::Table {
+        toml.parse().unwrap()
+    }
+
+    #[test]
+    fn db_name_is_stable() {
+        assert_eq!(
+            Config::default().as_db_name(),
+            "bevy-nomic-embed-text-latest-l2-e2e5c328"
+        );
+        let markdown = Config {
+            renderer: Renderer::Markdown,
+            ..Config::default()
+        };
+        assert_eq!(
+            markdown.as_db_name(),
+            "bevy-nomic-embed-text-latest-l2-markdown-7e32e969"
+        );
+    }
+
+    #[test]
+    fn save_writes_what_differs() {
+        let inherited = table(
+            r#"
+            target = "bevy"
+            distance = "l2"
+            [chroma]
+            url = "http://localhost:8000"
+            "#,
+        );
+        let mut project = table(
+            r#"
+            target = "bevy"
+            [ollama]
+            port = 1
+            models_dir = "models"
+            "#,
+        );
+        let current = table(
+            r#"
+            target = "bevy"
+            distance = "cosine"
+            [chroma]
+            url = "http://localhost:8000"
+            [ollama]
+            port = 2
+            "#,
+        );
+        update(&mut project, &inherited, current);
+        assert_eq!(
+            project,
+            table(
+                r#"
+                target = "bevy"
+                distance = "cosine"
+                [ollama]
+                port = 2
+                "#
+            )
+        );
+    }
+}
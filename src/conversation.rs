@@ -0,0 +1,115 @@
+use std::error::Error;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{answer::Answer, ollama::SimpleOllama, prompt::RetrievedDocument};
+
+const CONVERSATIONS_DIR: &str = "conversations";
+
+const SYSTEM: &str = "You rewrite the last question of a conversation about a Rust crate into \
+a standalone question that can be understood without the conversation, replacing pronouns and \
+references with the items they refer to. Reply with the rewritten question only.";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Turn {
+    pub question: String,
+    /// The question as used for retrieval, when it was rewritten from the conversation.
+    pub standalone_question: Option<String>,
+    pub answer: String,
+    pub citations: Vec<String>,
+    /// Documents the answer was given from.
+    #[serde(default)]
+    pub documents: Vec<RetrievedDocument>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Conversation {
+    pub turns: Vec<Turn>,
+}
+
+impl Conversation {
+    pub fn push(
+        &mut self,
+        question: String,
+        standalone_question: Option<String>,
+        answer: &Answer,
+        documents: Vec<RetrievedDocument>,
+    ) {
+        self.turns.push(Turn {
+            question,
+            standalone_question,
+            answer: answer.text.clone(),
+            citations: answer.citations.clone(),
+            documents,
+        });
+    }
+
+    /// Rewrites a follow-up question so that it stands on its own, for example "and how do I
+    /// remove it?" after a question about adding a component. Returns `None` when there is no
+    /// conversation to draw from.
+    pub async fn standalone_question(
+        &self,
+        ollama: &SimpleOllama,
+        question: &str,
+    ) -> Result<Option<String>, Box<dyn Error>> {
+        if self.turns.is_empty() {
+            return Ok(None);
+        }
+        let mut prompt = String::from("Conversation:\n\n");
+        for turn in &self.turns {
+            prompt.push_str(&format!("User: {}\n", turn.question));
+            prompt.push_str(&format!("Assistant: {}\n\n", turn.answer));
+        }
+        prompt.push_str(&format!("Last question: {}\n", question));
+        let rewritten = ollama.generate(SYSTEM, &prompt).await?;
+        Ok(Some(rewritten.trim().to_string()))
+    }
+
+    pub fn save(&self, name: &str) -> Result<(), Box<dyn Error>> {
+        let path = Self::path(name)?;
+        std::fs::create_dir_all(CONVERSATIONS_DIR)?;
+        let file = std::fs::File::create(path)?;
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
+
+    pub fn load(name: &str) -> Result<Self, Box<dyn Error>> {
+        let conversation = std::fs::read_to_string(Self::path(name)?)?;
+        Ok(serde_json::from_str(&conversation)?)
+    }
+
+    /// Names of the saved conversations.
+    pub fn list() -> Result<Vec<String>, Box<dyn Error>> {
+        let mut names = vec![];
+        let entries = match std::fs::read_dir(CONVERSATIONS_DIR) {
+            Ok(entries) => entries,
+            // Nothing saved yet.
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(names),
+            Err(err) => return Err(err.into()),
+        };
+        for entry in entries {
+            let path = entry?.path();
+            if let Some(name) = path.file_stem().and_then(|name| name.to_str()) {
+                names.push(name.to_string());
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    /// Names are limited to `[A-Za-z0-9_-]` so they stay inside the conversations directory.
+    fn path(name: &str) -> Result<String, Box<dyn Error>> {
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            return Err(format!(
+                "Invalid name '{}', use letters, digits, '_' and '-' only",
+                name
+            )
+            .into());
+        }
+        Ok(format!("{}/{}.json", CONVERSATIONS_DIR, name))
+    }
+}
@@ -1,25 +1,122 @@
 use std::collections::HashSet;
 use std::io::Write;
 
-type CrateCatalog = [Option<(String, rustdoc_types::Crate)>];
+use serde::{Deserialize, Serialize};
 
-pub fn generate_docs(entry: String) {
-    std::fs::create_dir_all("docs").unwrap();
+use crate::{config::Renderer, lexical::LexicalIndex, progress::Progress};
+
+/// The rustdoc JSON of a crate, at index 0, and of its dependencies, at the index of their
+/// `external_crates` id. Dependencies without a JSON are `None`.
+pub type CrateCatalog = [Option<(String, rustdoc_types::Crate)>];
+
+pub const INDEX_PATH: &str = "docs/index.json";
+
+/// Prefix of the metadata keys set to `true` for every module a document is in, see
+/// [`DocumentEntry::modules`]. Chroma can't match metadata by prefix, so the module filter
+/// checks one of these keys instead.
+pub const MODULE_KEY_PREFIX: &str = "in:";
+
+/// A generated document, as listed in [`INDEX_PATH`] for the embedding step.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentEntry {
+    pub id: String,
+    pub file: String,
+    pub path: String,
+    pub kind: String,
+    pub krate: String,
+    #[serde(default)]
+    pub module: String,
+    /// Module of the documented crate the item is re-exported at, like `bevy::render::camera`
+    /// for an item defined in `bevy_render::camera`.
+    #[serde(default)]
+    pub public_module: String,
+    #[serde(default)]
+    pub deprecated: bool,
+}
+
+impl DocumentEntry {
+    pub fn read_index() -> Result<Vec<DocumentEntry>, Box<dyn std::error::Error>> {
+        let index = std::fs::read_to_string(INDEX_PATH)?;
+        Ok(serde_json::from_str(&index)?)
+    }
+
+    /// Every module the item is in, by its defining and its public path, with their parents.
+    pub fn modules(&self) -> Vec<String> {
+        let mut modules = vec![];
+        for path in [&self.module, &self.public_module] {
+            let mut module = String::new();
+            for segment in path.split("::").filter(|segment| !segment.is_empty()) {
+                if !module.is_empty() {
+                    module.push_str("::");
+                }
+                module.push_str(segment);
+                if !modules.contains(&module) {
+                    modules.push(module.clone());
+                }
+            }
+        }
+        modules
+    }
+}
+
+/// Documents written so far, reported to the progress of the generation.
+struct Documents<'a> {
+    entries: Vec<DocumentEntry>,
+    renderer: Renderer,
+    progress: &'a Progress,
+}
+
+impl Documents<'_> {
+    fn push(&mut self, entry: DocumentEntry) {
+        self.entries.push(entry);
+        self.progress
+            .set(format!("Written {} documents", self.entries.len()));
+    }
+}
+
+pub fn generate_docs(
+    entry: String,
+    renderer: Renderer,
+    progress: &Progress,
+) -> Result<(), Box<dyn std::error::Error>> {
+    std::fs::create_dir_all("docs")?;
+    let loaded_crates = load_catalog(entry, progress)?;
+
+    let mut visited = HashSet::<(usize, rustdoc_types::Id)>::new();
+    let mut documents = Documents {
+        entries: vec![],
+        renderer,
+        progress,
+    };
+    start_krate(&loaded_crates, &mut visited, &mut documents);
+    if progress.is_cancelled() {
+        return Err("Cancelled".into());
+    }
+
+    progress.set("Indexing documents");
+    let index = std::fs::File::create(INDEX_PATH)?;
+    serde_json::to_writer_pretty(index, &documents.entries)?;
+    LexicalIndex::build(documents.entries)?.write()?;
+    Ok(())
+}
+
+/// Reads the rustdoc JSONs of `entry` and its dependencies from the jsons directory.
+pub fn load_catalog(
+    entry: String,
+    progress: &Progress,
+) -> Result<Vec<Option<(String, rustdoc_types::Crate)>>, Box<dyn std::error::Error>> {
+    progress.set("Loading rustdoc JSONs");
     let Ok(json_string) = std::fs::read_to_string(format!("./jsons/{}.json", entry)) else {
-        println!("Couldn't find {}.json", entry);
-        println!(
-                "You should generate all jsons from rustdoc and place them in the jsons directory by running the following commands:"
-            );
-        println!("You can run the following command in the project you want to document:");
-        println!();
-        println!("> RUSTDOCFLAGS=\"-Z unstable-options --output-format json\" cargo +nightly doc");
-        println!();
-        println!(
-                "then move the generated jsons from target/doc/ to the jsons directory in the rustdoc-rag project"
-            );
-        panic!()
+        return Err(format!(
+            "Couldn't find jsons/{}.json, generate the JSONs first or run \
+            `RUSTDOCFLAGS=\"-Z unstable-options --output-format json\" cargo +nightly doc` in the \
+            project you want to document and move target/doc/ to jsons/",
+            entry
+        )
+        .into());
     };
-    let krate: rustdoc_types::Crate = serde_json::from_str(&json_string).unwrap();
+    let krate: rustdoc_types::Crate = serde_json::from_str(&json_string)
+        .map_err(|err| format!("Couldn't parse json for crate {}: {}", entry, err))?;
 
     let mut loaded_crates = vec![None; krate.external_crates.len() + 1];
 
@@ -27,34 +124,44 @@ pub fn generate_docs(entry: String) {
         if ext_krate.1.name == "typenum" {
             continue;
         }
+        progress.set(format!("Loading rustdoc JSON of {}", ext_krate.1.name));
         let Ok(json_string) = std::fs::read_to_string(format!("./jsons/{}.json", ext_krate.1.name))
         else {
             continue;
         };
-        let Ok(krate) = serde_json::from_str(&json_string) else {
-            panic!("Couldn't parse json for crate {}", ext_krate.1.name);
-        };
+        let krate = serde_json::from_str(&json_string).map_err(|err| {
+            format!(
+                "Couldn't parse json for crate {}: {}",
+                ext_krate.1.name, err
+            )
+        })?;
         loaded_crates[*ext_krate.0 as usize] = Some((ext_krate.1.name.clone(), krate));
     }
-    loaded_crates[0] = Some(("bevy".to_string(), krate));
-
-    let mut visited = HashSet::<(usize, rustdoc_types::Id)>::new();
-    start_krate(&loaded_crates, &mut visited);
+    loaded_crates[0] = Some((entry, krate));
+    Ok(loaded_crates)
 }
 
-fn start_krate(crates: &CrateCatalog, visited: &mut HashSet<(usize, rustdoc_types::Id)>) {
+fn start_krate(
+    crates: &CrateCatalog,
+    visited: &mut HashSet<(usize, rustdoc_types::Id)>,
+    documents: &mut Documents,
+) {
     let krate = &crates[0].as_ref().unwrap().1;
-    item_explorer(krate.root, 0, crates, visited, 0);
+    item_explorer(krate.root, 0, crates, visited, documents, 0, &[]);
 }
 
+/// Documents the item `id` and what it contains. `location` is the public path of the module
+/// it was reached from.
 fn item_explorer(
     id: rustdoc_types::Id,
     current_crate: usize,
     crates: &CrateCatalog,
     visited: &mut HashSet<(usize, rustdoc_types::Id)>,
+    documents: &mut Documents,
     depth: u32,
+    location: &[String],
 ) {
-    if !visited.insert((current_crate, id)) {
+    if documents.progress.is_cancelled() || !visited.insert((current_crate, id)) {
         return;
     }
     let krate = crates[current_crate].as_ref().unwrap();
@@ -65,40 +172,99 @@ fn item_explorer(
     };
     match &item.inner {
         rustdoc_types::ItemEnum::Module(module) => {
-            module_explorer(module, current_crate, crates, visited, depth);
+            let mut location = location.to_vec();
+            location.push(item.name.clone().unwrap_or_else(|| krate.0.clone()));
+            module_explorer(
+                module,
+                current_crate,
+                crates,
+                visited,
+                documents,
+                depth,
+                &location,
+            );
         }
         rustdoc_types::ItemEnum::ExternCrate { .. } => todo!(),
         rustdoc_types::ItemEnum::Use(used) => {
-            let crate_name = used.source.split("::").next().unwrap();
-            if crate_name == "crate" || crate_name == "super" {
-                return item_explorer(used.id.unwrap(), current_crate, crates, visited, depth + 1);
-            }
-            for (crate_index, krate) in crates.iter().enumerate() {
-                if let Some(krate) = krate {
-                    if krate.0 == crate_name {
-                        return item_explorer(
-                            rustdoc_types::Id(u32::MAX),
-                            crate_index,
-                            crates,
-                            visited,
-                            depth + 1,
-                        );
-                    }
+            let Some((target_crate, target)) = use_target(used, current_crate, crates) else {
+                return;
+            };
+            let target_krate = &crates[target_crate].as_ref().unwrap().1;
+            // Re-exported modules are documented under the name they are re-exported as, and
+            // the items of a glob import in the module importing them.
+            if let Some(rustdoc_types::ItemEnum::Module(module)) =
+                target_krate.index.get(&target).map(|item| &item.inner)
+            {
+                if !visited.insert((target_crate, target)) {
+                    return;
                 }
+                let mut location = location.to_vec();
+                if !used.is_glob {
+                    location.push(used.name.clone());
+                }
+                return module_explorer(
+                    module,
+                    target_crate,
+                    crates,
+                    visited,
+                    documents,
+                    depth + 1,
+                    &location,
+                );
             }
-            return item_explorer(used.id.unwrap(), current_crate, crates, visited, depth + 1);
+            return item_explorer(
+                target,
+                target_crate,
+                crates,
+                visited,
+                documents,
+                depth + 1,
+                location,
+            );
         }
         rustdoc_types::ItemEnum::Union(_union) => todo!(),
         rustdoc_types::ItemEnum::Struct(stru) => {
-            document_struct(item, stru, current_crate, crates);
+            documents.push(document_struct(
+                item,
+                stru,
+                current_crate,
+                crates,
+                location,
+                documents.renderer,
+            ));
         }
         rustdoc_types::ItemEnum::StructField(_strufi) => {}
         rustdoc_types::ItemEnum::Enum(enume) => {
-            enum_explorer(enume, current_crate, crates, visited, depth);
+            documents.push(document_enum(
+                item,
+                enume,
+                current_crate,
+                crates,
+                location,
+                documents.renderer,
+            ));
+            enum_explorer(
+                enume,
+                current_crate,
+                crates,
+                visited,
+                documents,
+                depth,
+                location,
+            );
         }
         rustdoc_types::ItemEnum::Variant(_) => {}
         rustdoc_types::ItemEnum::Function(_) => {}
-        rustdoc_types::ItemEnum::Trait(_) => {}
+        rustdoc_types::ItemEnum::Trait(tra) => {
+            documents.push(document_trait(
+                item,
+                tra,
+                current_crate,
+                crates,
+                location,
+                documents.renderer,
+            ));
+        }
         rustdoc_types::ItemEnum::TraitAlias(_) => todo!(),
         rustdoc_types::ItemEnum::Impl(_) => {}
         rustdoc_types::ItemEnum::TypeAlias(_) => {}
@@ -113,15 +279,62 @@ fn item_explorer(
     }
 }
 
+/// The crate and id of the item re-exported by `used`. Items of another crate are looked up by
+/// path, falling back to the root of that crate.
+fn use_target(
+    used: &rustdoc_types::Use,
+    current_crate: usize,
+    crates: &CrateCatalog,
+) -> Option<(usize, rustdoc_types::Id)> {
+    let segments = used.source.split("::").collect::<Vec<_>>();
+    if segments[0] != "crate" && segments[0] != "super" {
+        for (crate_index, krate) in crates.iter().enumerate() {
+            let Some((name, krate)) = krate else {
+                continue;
+            };
+            if name.as_str() != segments[0] {
+                continue;
+            }
+            let target = krate
+                .paths
+                .iter()
+                .find(|(_, summary)| {
+                    summary.crate_id == 0
+                        && summary.path.len() == segments.len()
+                        && summary
+                            .path
+                            .iter()
+                            .zip(&segments)
+                            .skip(1)
+                            .all(|(a, b)| a == *b)
+                })
+                .map(|(id, _)| *id)
+                .unwrap_or(krate.root);
+            return Some((crate_index, target));
+        }
+    }
+    Some((current_crate, used.id?))
+}
+
 fn module_explorer(
     module: &rustdoc_types::Module,
     current_crate: usize,
     crates: &CrateCatalog,
     visited: &mut HashSet<(usize, rustdoc_types::Id)>,
+    documents: &mut Documents,
     depth: u32,
+    location: &[String],
 ) {
     for item in &module.items {
-        item_explorer(*item, current_crate, crates, visited, depth + 1);
+        item_explorer(
+            *item,
+            current_crate,
+            crates,
+            visited,
+            documents,
+            depth + 1,
+            location,
+        );
     }
 }
 
@@ -130,17 +343,32 @@ fn enum_explorer(
     current_crate: usize,
     crates: &CrateCatalog,
     visited: &mut HashSet<(usize, rustdoc_types::Id)>,
+    documents: &mut Documents,
     depth: u32,
+    location: &[String],
 ) {
     enumeration.variants.iter().for_each(|variant| {
-        item_explorer(*variant, current_crate, crates, visited, depth + 1);
+        item_explorer(
+            *variant,
+            current_crate,
+            crates,
+            visited,
+            documents,
+            depth + 1,
+            location,
+        );
     });
 }
 
-struct StructDocument {
+struct ItemDocument {
     name: String,
+    path: Vec<String>,
+    public_module: String,
+    kind: &'static str,
     docs: Option<String>,
-    fields: Vec<Field>,
+    deprecated: bool,
+    member_kind: &'static str,
+    members: Vec<Field>,
 }
 
 struct Field {
@@ -148,64 +376,172 @@ struct Field {
     docs: Option<String>,
 }
 
+fn item_document(
+    item: &rustdoc_types::Item,
+    kind: &'static str,
+    member_kind: &'static str,
+    members: &[rustdoc_types::Id],
+    current_crate: usize,
+    crates: &CrateCatalog,
+    location: &[String],
+) -> ItemDocument {
+    let name = item.name.as_ref().unwrap().to_string();
+    let krate = &crates[current_crate].as_ref().unwrap().1;
+    ItemDocument {
+        path: krate
+            .paths
+            .get(&item.id)
+            .map(|summary| summary.path.clone())
+            .unwrap_or_else(|| vec![name.clone()]),
+        name,
+        public_module: location.join("::"),
+        kind,
+        docs: item.docs.clone(),
+        deprecated: item.deprecation.is_some(),
+        member_kind,
+        members: members
+            .iter()
+            .filter_map(|member| krate.index.get(member))
+            .filter_map(|member| {
+                Some(Field {
+                    name: member.name.as_ref()?.to_string(),
+                    docs: member.docs.clone(),
+                })
+            })
+            .collect(),
+    }
+}
+
 pub fn document_struct(
     item: &rustdoc_types::Item,
     stru: &rustdoc_types::Struct,
     current_crate: usize,
     crates: &CrateCatalog,
-) {
-    std::fs::create_dir_all("docs/structs").unwrap();
-    let mut doc = StructDocument {
-        name: item.name.as_ref().unwrap().to_string(),
-        docs: item.docs.clone(),
-        fields: vec![],
+    location: &[String],
+    renderer: Renderer,
+) -> DocumentEntry {
+    let fields = match &stru.kind {
+        rustdoc_types::StructKind::Unit => &[][..],
+        rustdoc_types::StructKind::Tuple(_fields) => &[][..],
+        rustdoc_types::StructKind::Plain { fields, .. } => &fields[..],
     };
+    item_document(
+        item,
+        "struct",
+        "field",
+        fields,
+        current_crate,
+        crates,
+        location,
+    )
+    .write(renderer)
+}
 
-    match &stru.kind {
-        rustdoc_types::StructKind::Unit => {}
-        rustdoc_types::StructKind::Tuple(_fields) => {}
-        rustdoc_types::StructKind::Plain { fields, .. } => {
-            doc.fields = fields
-                .iter()
-                .map(|field| {
-                    let field = crates
-                        .get(current_crate)
-                        .unwrap()
-                        .as_ref()
-                        .unwrap()
-                        .1
-                        .index
-                        .get(field)
-                        .unwrap();
-                    Field {
-                        name: field.name.as_ref().unwrap().to_string(),
-                        docs: field.docs.clone(),
-                    }
-                })
-                .collect();
-        }
-    }
-    doc.write();
+pub fn document_enum(
+    item: &rustdoc_types::Item,
+    enumeration: &rustdoc_types::Enum,
+    current_crate: usize,
+    crates: &CrateCatalog,
+    location: &[String],
+    renderer: Renderer,
+) -> DocumentEntry {
+    item_document(
+        item,
+        "enum",
+        "variant",
+        &enumeration.variants,
+        current_crate,
+        crates,
+        location,
+    )
+    .write(renderer)
 }
 
-impl StructDocument {
-    pub fn write(&self) {
-        let mut file = std::fs::File::create(format!("docs/structs/{}.md", self.name)).unwrap();
+pub fn document_trait(
+    item: &rustdoc_types::Item,
+    tra: &rustdoc_types::Trait,
+    current_crate: usize,
+    crates: &CrateCatalog,
+    location: &[String],
+    renderer: Renderer,
+) -> DocumentEntry {
+    item_document(
+        item,
+        "trait",
+        "item",
+        &tra.items,
+        current_crate,
+        crates,
+        location,
+    )
+    .write(renderer)
+}
+
+impl ItemDocument {
+    pub fn write(&self, renderer: Renderer) -> DocumentEntry {
+        std::fs::create_dir_all(format!("docs/{}s", self.kind)).unwrap();
+        let path = self.path.join("::");
+        let file_name = format!("docs/{}s/{}.md", self.kind, self.path.join("."));
+        let mut file = std::fs::File::create(&file_name).unwrap();
+        match renderer {
+            Renderer::Prose => self.write_prose(&mut file),
+            Renderer::Markdown => self.write_markdown(&mut file, &path),
+        }
+
+        DocumentEntry {
+            id: path.clone(),
+            file: file_name,
+            krate: self.path[0].clone(),
+            module: self.path[..self.path.len() - 1].join("::"),
+            public_module: self.public_module.clone(),
+            path,
+            kind: self.kind.to_string(),
+            deprecated: self.deprecated,
+        }
+    }
 
-        write!(file, "{} is a struct.\n\n", self.name).unwrap();
+    fn write_prose(&self, file: &mut std::fs::File) {
+        write!(file, "{} is a {}.\n\n", self.name, self.kind).unwrap();
+        if self.deprecated {
+            write!(file, "It is deprecated.\n\n").unwrap();
+        }
         if let Some(docs) = &self.docs {
             write!(file, "{}\n\n", docs).unwrap();
         }
-        if !self.fields.is_empty() {
-            write!(file, "It has the following fields: ").unwrap();
-            for field in &self.fields {
-                write!(file, "{}, ", field.name).unwrap();
+        if !self.members.is_empty() {
+            write!(file, "It has the following {}s: ", self.member_kind).unwrap();
+            for member in &self.members {
+                write!(file, "{}, ", member.name).unwrap();
             }
             write!(file, "\n\n").unwrap();
 
-            for field in &self.fields {
-                if let Some(docs) = &field.docs {
-                    write!(file, "More details about the {} field:\n\n", field.name).unwrap();
+            for member in &self.members {
+                if let Some(docs) = &member.docs {
+                    write!(
+                        file,
+                        "More details about the {} {}:\n\n",
+                        member.name, self.member_kind
+                    )
+                    .unwrap();
+                    write!(file, "{}\n\n", docs).unwrap();
+                }
+            }
+        }
+    }
+
+    fn write_markdown(&self, file: &mut std::fs::File, path: &str) {
+        write!(file, "# {} `{}`\n\n", self.kind, path).unwrap();
+        if self.deprecated {
+            write!(file, "**Deprecated.**\n\n").unwrap();
+        }
+        if let Some(docs) = &self.docs {
+            write!(file, "{}\n\n", docs).unwrap();
+        }
+        if !self.members.is_empty() {
+            write!(file, "## {}s\n\n", self.member_kind).unwrap();
+            for member in &self.members {
+                write!(file, "### `{}`\n\n", member.name).unwrap();
+                if let Some(docs) = &member.docs {
                     write!(file, "{}\n\n", docs).unwrap();
                 }
             }
@@ -1,34 +1,62 @@
-use chromadb::v2::{collection::CollectionEntries, ChromaClient};
+use std::{
+    error::Error,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use chromadb::v2::collection::CollectionEntries;
 use serde_json::Map;
 
-use crate::ollama::SimpleOllama;
+use crate::{
+    collections,
+    config::Config,
+    document::{DocumentEntry, MODULE_KEY_PREFIX},
+    ollama::SimpleOllama,
+};
 
+/// Indexes the documents in the collection of `config`.
 pub async fn generate_embeddings(
     ollama: SimpleOllama,
-    collection_name: &str,
-    distance: &str,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let chroma: ChromaClient = ChromaClient::new(Default::default());
+    config: &Config,
+) -> Result<(), Box<dyn Error>> {
+    let chroma = config.chroma.client()?;
 
-    let mut collection_meta = Map::new();
-    collection_meta.insert("hnsw:space".to_string(), distance.into());
+    let collection_name = config.as_db_name();
     let collection = chroma
-        .get_or_create_collection(&collection_name, Some(collection_meta))
+        .get_or_create_collection(
+            &collection_name,
+            Some(collections::creation_metadata(config)),
+        )
         .await?;
 
-    let dir = std::fs::read_dir("./docs/structs")?;
-    for entry in dir {
-        let entry = entry.unwrap();
-        let path = entry.path();
-        let file_name = path.file_name().unwrap().to_str().unwrap();
+    for document in DocumentEntry::read_index()? {
+        let text = std::fs::read_to_string(&document.file)?;
+        let mut metadata = Map::new();
+        metadata.insert("path".to_string(), document.path.into());
+        metadata.insert("kind".to_string(), document.kind.into());
+        metadata.insert("crate".to_string(), document.krate.into());
+        for module in document.modules() {
+            metadata.insert(format!("{}{}", MODULE_KEY_PREFIX, module), true.into());
+        }
+        metadata.insert("module".to_string(), document.module.into());
+        metadata.insert("deprecated".to_string(), document.deprecated.into());
         let entries = CollectionEntries {
-            ids: vec![file_name],
-            embeddings: Some(vec![
-                ollama.embeddings(&std::fs::read_to_string(&path)?).await?,
-            ]),
-            ..Default::default()
+            ids: vec![document.id.as_str()],
+            embeddings: Some(vec![ollama.embeddings(&text).await?]),
+            metadatas: Some(vec![metadata]),
+            documents: Some(vec![text.as_str()]),
         };
         collection.upsert(entries, None).await?;
     }
+    // Existing collections get their metadata refreshed, and the indexing time recorded where
+    // every client of the database sees it.
+    let mut metadata = collections::metadata(config);
+    metadata.insert(
+        collections::INDEXED_AT_KEY.to_string(),
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)?
+            .as_secs()
+            .into(),
+    );
+    collection.modify(None, Some(&metadata)).await?;
     Ok(())
 }
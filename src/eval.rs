@@ -0,0 +1,206 @@
+use std::error::Error;
+
+use serde::Deserialize;
+
+use crate::{
+    config::Config,
+    ollama::SimpleOllama,
+    prompt::{retrieve, Query, RetrieveOptions},
+};
+
+/// A query with the item paths a good retrieval should return for it. The query can start with
+/// retrieve options, as read by [`Query::parse`].
+#[derive(Debug, Clone, Deserialize)]
+pub struct GoldenQuery {
+    pub query: String,
+    pub expected: Vec<String>,
+}
+
+impl GoldenQuery {
+    /// Reads a JSON list of golden queries.
+    pub fn read(path: &str) -> Result<Vec<GoldenQuery>, Box<dyn Error>> {
+        let queries = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&queries)?)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Metrics {
+    pub recall: f32,
+    pub reciprocal_rank: f32,
+    pub ndcg: f32,
+}
+
+impl Metrics {
+    /// Metrics of the `k` first `retrieved` paths, with binary relevance. Everything is 0 when
+    /// nothing is expected or `k` is 0.
+    pub fn score(expected: &[String], retrieved: &[String], k: usize) -> Self {
+        if expected.is_empty() {
+            return Metrics::default();
+        }
+        let retrieved = &retrieved[..retrieved.len().min(k)];
+        let relevant = retrieved
+            .iter()
+            .map(|path| expected.contains(path))
+            .collect::<Vec<_>>();
+
+        let found = relevant.iter().filter(|relevant| **relevant).count();
+        let reciprocal_rank = relevant
+            .iter()
+            .position(|relevant| *relevant)
+            .map_or(0.0, |rank| 1.0 / (rank as f32 + 1.0));
+        let gain = |rank: usize| 1.0 / (rank as f32 + 2.0).log2();
+        let dcg = relevant
+            .iter()
+            .enumerate()
+            .filter(|(_, relevant)| **relevant)
+            .map(|(rank, _)| gain(rank))
+            .sum::<f32>();
+        let ideal = (0..expected.len().min(k)).map(gain).sum::<f32>();
+
+        Metrics {
+            recall: found as f32 / expected.len() as f32,
+            reciprocal_rank,
+            ndcg: if ideal > 0.0 { dcg / ideal } else { 0.0 },
+        }
+    }
+
+    pub fn mean(metrics: &[Metrics]) -> Self {
+        let count = metrics.len().max(1) as f32;
+        Metrics {
+            recall: metrics.iter().map(|m| m.recall).sum::<f32>() / count,
+            reciprocal_rank: metrics.iter().map(|m| m.reciprocal_rank).sum::<f32>() / count,
+            ndcg: metrics.iter().map(|m| m.ndcg).sum::<f32>() / count,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub config: Config,
+    pub collection: String,
+    /// Metrics of each query, in the order of the golden set.
+    pub queries: Vec<Metrics>,
+    /// recall@k, MRR and nDCG@k over the golden set.
+    pub mean: Metrics,
+}
+
+/// Runs every golden query against `collection`, indexed with `config`.
+pub async fn evaluate(
+    config: &Config,
+    collection: &str,
+    queries: &[GoldenQuery],
+    defaults: &RetrieveOptions,
+) -> Result<Report, Box<dyn Error>> {
+    let ollama = SimpleOllama::new(
+        &config.ollama,
+        config.embedding_model.clone(),
+        config.chat_model.clone(),
+    );
+    let mut metrics = vec![];
+    for golden in queries {
+        let query = Query::parse(&golden.query, defaults);
+        let retrieved = retrieve(
+            &ollama,
+            &config.chroma,
+            collection,
+            &query.text,
+            &query.options,
+        )
+        .await?
+        .into_iter()
+        .map(|document| document.path)
+        .collect::<Vec<_>>();
+        metrics.push(Metrics::score(
+            &golden.expected,
+            &retrieved,
+            query.options.top_k,
+        ));
+    }
+    Ok(Report {
+        config: config.clone(),
+        collection: collection.to_string(),
+        mean: Metrics::mean(&metrics),
+        queries: metrics,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(paths: &[&str]) -> Vec<String> {
+        paths.iter().map(|path| path.to_string()).collect()
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-5,
+            "{} isn't {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn all_expected_found() {
+        let metrics = Metrics::score(&paths(&["a", "b"]), &paths(&["x", "a", "y", "b"]), 4);
+        assert_close(metrics.recall, 1.0);
+        assert_close(metrics.reciprocal_rank, 0.5);
+        // (1 / log2(3) + 1 / log2(5)) / (1 + 1 / log2(3))
+        assert_close(metrics.ndcg, 0.650_920_9);
+    }
+
+    #[test]
+    fn only_the_first_k_count() {
+        let metrics = Metrics::score(&paths(&["a", "b"]), &paths(&["x", "a", "y", "b"]), 2);
+        assert_close(metrics.recall, 0.5);
+        assert_close(metrics.reciprocal_rank, 0.5);
+        // (1 / log2(3)) / (1 + 1 / log2(3))
+        assert_close(metrics.ndcg, 0.386_852_8);
+    }
+
+    #[test]
+    fn perfect_ranking() {
+        let metrics = Metrics::score(&paths(&["a", "b"]), &paths(&["a", "b", "x"]), 3);
+        assert_eq!(
+            metrics,
+            Metrics {
+                recall: 1.0,
+                reciprocal_rank: 1.0,
+                ndcg: 1.0
+            }
+        );
+    }
+
+    #[test]
+    fn nothing_found() {
+        let metrics = Metrics::score(&paths(&["a"]), &paths(&["x", "y"]), 2);
+        assert_eq!(metrics, Metrics::default());
+    }
+
+    #[test]
+    fn no_nan() {
+        assert_eq!(
+            Metrics::score(&paths(&["a"]), &paths(&["a"]), 0),
+            Metrics::default()
+        );
+        assert_eq!(Metrics::score(&[], &paths(&["a"]), 5), Metrics::default());
+    }
+
+    #[test]
+    fn mean() {
+        let mean = Metrics::mean(&[
+            Metrics {
+                recall: 1.0,
+                reciprocal_rank: 0.5,
+                ndcg: 0.25,
+            },
+            Metrics::default(),
+        ]);
+        assert_close(mean.recall, 0.5);
+        assert_close(mean.reciprocal_rank, 0.25);
+        assert_close(mean.ndcg, 0.125);
+        assert_eq!(Metrics::mean(&[]), Metrics::default());
+    }
+}
@@ -0,0 +1,67 @@
+use std::error::Error;
+
+use crate::ollama::SimpleOllama;
+
+const HYDE_SYSTEM: &str = "You write the documentation of the Rust item that would answer the \
+question of the user, as found in rustdoc: what the item is, what it is used for and how. \
+Reply with the documentation only.";
+
+const EXPANSION_SYSTEM: &str = "You rephrase questions about a Rust crate to search its \
+documentation. Reply with one rephrased question per line, using different words and naming \
+the likely types, traits or functions involved, nothing else.";
+
+/// Hypothetical documentation answering `prompt` (HyDE). Its embedding is usually closer to the
+/// relevant documents than the one of a short prompt.
+pub async fn hypothetical_document(
+    ollama: &SimpleOllama,
+    prompt: &str,
+) -> Result<String, Box<dyn Error>> {
+    ollama.generate(HYDE_SYSTEM, prompt).await
+}
+
+/// Up to `count` rephrasings of `prompt`, to retrieve for each of them and fuse the results.
+pub async fn expand_query(
+    ollama: &SimpleOllama,
+    prompt: &str,
+    count: usize,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let expansions = ollama
+        .generate(
+            EXPANSION_SYSTEM,
+            &format!("Give {} rephrasings of: {}", count, prompt),
+        )
+        .await?;
+    Ok(expansions
+        .lines()
+        .map(|line| {
+            line.trim()
+                .trim_start_matches(|c: char| c.is_ascii_digit() || c == '.' || c == '-')
+                .trim()
+                .to_string()
+        })
+        .filter(|line| !line.is_empty())
+        .take(count)
+        .collect())
+}
+
+/// Rewrites the code identifiers of `prompt` the way generated documents mention items, so
+/// that `Query<&Transform>` also searches for "Query is a" and "Transform is a", and
+/// `despawn_recursive` for "despawn recursive". A capitalized first word is taken for the
+/// start of a sentence unless it is in camel case.
+pub fn rewrite_identifiers(prompt: &str) -> String {
+    let mut rewritten = prompt.to_string();
+    let words = prompt
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|word| !word.is_empty());
+    for (i, word) in words.enumerate() {
+        let mut chars = word.chars();
+        let first_uppercase = chars.next().is_some_and(|c| c.is_uppercase());
+        let camel_case = chars.clone().any(|c| c.is_uppercase());
+        if word.contains('_') {
+            rewritten.push_str(&format!(". {}", word.replace('_', " ")));
+        } else if first_uppercase && chars.any(|c| c.is_lowercase()) && (i > 0 || camel_case) {
+            rewritten.push_str(&format!(". {} is a", word));
+        }
+    }
+    rewritten
+}
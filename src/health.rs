@@ -0,0 +1,77 @@
+use std::time::{Duration, Instant, SystemTime};
+
+use tokio::time::timeout;
+
+use crate::{collections, config::Config};
+
+/// How long a service has to answer a health check.
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Default)]
+pub struct ServiceHealth {
+    /// Round trip of the check, `None` when the service didn't answer.
+    pub latency: Option<Duration>,
+    pub version: Option<String>,
+}
+
+impl ServiceHealth {
+    pub fn is_up(&self) -> bool {
+        self.latency.is_some()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Health {
+    pub chroma: ServiceHealth,
+    pub ollama: ServiceHealth,
+    /// Whether Ollama has the embedding model of the config.
+    pub embedding_model: bool,
+    /// Whether Ollama has the chat model of the config.
+    pub chat_model: bool,
+    /// Whether the collection of the config exists.
+    pub collection: bool,
+    /// Documents in the collection of the config.
+    pub documents: Option<usize>,
+    pub indexed_at: Option<SystemTime>,
+}
+
+/// Checks the services of `config` and the state of its collection.
+pub async fn check(config: &Config) -> Health {
+    let mut health = Health::default();
+
+    let start = Instant::now();
+    if let Ok(Ok(version)) = timeout(TIMEOUT, config.ollama.version()).await {
+        health.ollama = ServiceHealth {
+            latency: Some(start.elapsed()),
+            version: Some(version),
+        };
+        if let Ok(Ok(models)) = timeout(TIMEOUT, config.ollama.client().list_local_models()).await {
+            let has = |name: &str| models.iter().any(|model| model.name == name);
+            health.embedding_model = has(&config.embedding_model);
+            health.chat_model = has(&config.chat_model);
+        }
+    }
+
+    let Ok(chroma) = config.chroma.client() else {
+        return health;
+    };
+    let start = Instant::now();
+    if let Ok(Ok(version)) = timeout(TIMEOUT, chroma.version()).await {
+        health.chroma = ServiceHealth {
+            latency: Some(start.elapsed()),
+            version: Some(version),
+        };
+    } else {
+        return health;
+    }
+    if let Ok(Ok(collection)) = timeout(TIMEOUT, chroma.get_collection(&config.as_db_name())).await
+    {
+        health.collection = true;
+        health.documents = timeout(TIMEOUT, collection.count())
+            .await
+            .ok()
+            .and_then(Result::ok);
+        health.indexed_at = collections::indexed_at(&collection);
+    }
+    health
+}
@@ -1,35 +1,88 @@
-use xshell;
+use std::{
+    error::Error,
+    process::Stdio,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::process::Command;
+
+use crate::{
+    process::{collect, ProcessFailed},
+    progress::Progress,
+};
+
+/// Lines of error output kept from a failed command, cargo prints the error before a summary.
+const OUTPUT_LINES: usize = 5;
+
+/// Builds the rustdoc JSONs of `krate` and its dependencies into the jsons directory. The
+/// previous JSONs are only replaced once the new ones are built.
+pub async fn generate_jsons(krate: String, progress: &Progress) -> Result<(), Box<dyn Error>> {
+    // Left behind by a cancelled run.
+    let _ = std::fs::remove_dir_all("./tmp-crate");
+
+    progress.set("Creating a crate depending on it");
+    run(Command::new("cargo").args(["new", "tmp-crate"]), progress).await?;
+    run(
+        Command::new("cargo")
+            .args(["add", krate.as_str()])
+            .current_dir("tmp-crate"),
+        progress,
+    )
+    .await?;
+
+    progress.set(format!(
+        "Building the rustdoc JSONs of {} and its dependencies",
+        krate
+    ));
+    run(
+        Command::new("cargo")
+            .env("RUSTDOCFLAGS", "-Z unstable-options --output-format json")
+            .args(["+nightly", "doc"])
+            .current_dir("tmp-crate"),
+        progress,
+    )
+    .await?;
 
-pub fn generate_jsons(krate: String) {
     let _ = std::fs::remove_dir_all("./jsons");
-    let shell = xshell::Shell::new().unwrap();
-    shell
-        .cmd("cargo")
-        .args(["new", "tmp-crate"])
-        .quiet()
-        .ignore_stderr()
-        .ignore_stdout()
-        .run()
-        .unwrap();
-
-    shell.change_dir("tmp-crate");
-    shell
-        .cmd("cargo")
-        .args(["add", krate.as_str()])
-        .quiet()
-        .ignore_stderr()
-        .ignore_stdout()
-        .run()
-        .unwrap();
-    shell
-        .cmd("cargo")
-        .env("RUSTDOCFLAGS", "-Z unstable-options --output-format json")
-        .args(["+nightly", "doc"])
-        .quiet()
-        .ignore_stderr()
-        .ignore_stdout()
-        .run()
-        .unwrap();
-    std::fs::rename("./tmp-crate/target/doc", "./jsons").unwrap();
-    std::fs::remove_dir_all("./tmp-crate").unwrap();
+    std::fs::rename("./tmp-crate/target/doc", "./jsons")?;
+    std::fs::remove_dir_all("./tmp-crate")?;
+    Ok(())
+}
+
+/// Runs `command` to completion, killing it if `progress` is cancelled.
+async fn run(command: &mut Command, progress: &Progress) -> Result<(), Box<dyn Error>> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    let output = Arc::new(Mutex::new(vec![]));
+    let collecting = child
+        .stderr
+        .take()
+        .map(|stderr| tokio::spawn(collect(stderr, output.clone())));
+    loop {
+        if let Some(status) = child.try_wait()? {
+            if !status.success() {
+                // Until the end of the output, written right before exiting.
+                if let Some(collecting) = collecting {
+                    let _ = collecting.await;
+                }
+                let output = output.lock().unwrap();
+                return Err(ProcessFailed {
+                    message: format!("{:?} failed: {}", command.as_std(), status),
+                    output: output[output.len().saturating_sub(OUTPUT_LINES)..].to_vec(),
+                }
+                .into());
+            }
+            return Ok(());
+        }
+        if progress.is_cancelled() {
+            child.kill().await?;
+            return Err("Cancelled".into());
+        }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
 }
@@ -0,0 +1,224 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::document::DocumentEntry;
+
+pub const LEXICAL_INDEX_PATH: &str = "docs/lexical.json";
+
+const K1: f32 = 1.2;
+const B: f32 = 0.75;
+/// Item paths are short compared to the documentation, repeating their terms makes an exact
+/// identifier match outweigh a mention in the text.
+const PATH_BOOST: u32 = 3;
+
+/// A BM25 index over the generated documents, matching their text and item paths.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct LexicalIndex {
+    pub documents: Vec<DocumentEntry>,
+    lengths: Vec<u32>,
+    postings: HashMap<String, Vec<(usize, u32)>>,
+}
+
+impl LexicalIndex {
+    pub fn build(documents: Vec<DocumentEntry>) -> Result<Self, Box<dyn std::error::Error>> {
+        let texts = documents
+            .iter()
+            .map(|document| std::fs::read_to_string(&document.file))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self::from_texts(documents, &texts))
+    }
+
+    fn from_texts(documents: Vec<DocumentEntry>, texts: &[String]) -> Self {
+        let mut index = LexicalIndex::default();
+        for (i, (document, text)) in documents.iter().zip(texts).enumerate() {
+            let mut frequencies = HashMap::<String, u32>::new();
+            for token in tokenize(text) {
+                *frequencies.entry(token).or_default() += 1;
+            }
+            for token in tokenize(&document.path) {
+                *frequencies.entry(token).or_default() += PATH_BOOST;
+            }
+            index.lengths.push(frequencies.values().sum());
+            for (token, frequency) in frequencies {
+                index
+                    .postings
+                    .entry(token)
+                    .or_default()
+                    .push((i, frequency));
+            }
+        }
+        index.documents = documents;
+        index
+    }
+
+    pub fn write(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let file = std::fs::File::create(LEXICAL_INDEX_PATH)?;
+        serde_json::to_writer(file, self)?;
+        Ok(())
+    }
+
+    pub fn read() -> Result<Self, Box<dyn std::error::Error>> {
+        let index = std::fs::read_to_string(LEXICAL_INDEX_PATH)?;
+        Ok(serde_json::from_str(&index)?)
+    }
+
+    /// The index last read, read again when the file changed. `None` when there is no index.
+    pub fn cached() -> Result<Option<Arc<Self>>, Box<dyn std::error::Error>> {
+        static CACHE: Mutex<Option<(SystemTime, Arc<LexicalIndex>)>> = Mutex::new(None);
+
+        let Ok(modified) =
+            std::fs::metadata(LEXICAL_INDEX_PATH).and_then(|metadata| metadata.modified())
+        else {
+            return Ok(None);
+        };
+        let mut cache = CACHE.lock().unwrap();
+        if let Some((read_at, index)) = cache.as_ref() {
+            if *read_at == modified {
+                return Ok(Some(index.clone()));
+            }
+        }
+        let index = Arc::new(Self::read()?);
+        *cache = Some((modified, index.clone()));
+        Ok(Some(index))
+    }
+
+    /// Documents matching `query`, best first, with their BM25 score.
+    pub fn search(&self, query: &str) -> Vec<(&DocumentEntry, f32)> {
+        let count = self.documents.len() as f32;
+        let average_length = self.lengths.iter().sum::<u32>() as f32 / count.max(1.0);
+        let mut scores = HashMap::<usize, f32>::new();
+        for token in tokenize(query) {
+            let Some(postings) = self.postings.get(&token) else {
+                continue;
+            };
+            let matching = postings.len() as f32;
+            let idf = ((count - matching + 0.5) / (matching + 0.5) + 1.0).ln();
+            for (document, frequency) in postings {
+                let frequency = *frequency as f32;
+                let length = self.lengths[*document] as f32;
+                *scores.entry(*document).or_default() += idf * frequency * (K1 + 1.0)
+                    / (frequency + K1 * (1.0 - B + B * length / average_length));
+            }
+        }
+        let mut scores = scores
+            .into_iter()
+            .map(|(document, score)| (&self.documents[document], score))
+            .collect::<Vec<_>>();
+        scores.sort_by(|a, b| b.1.total_cmp(&a.1));
+        scores
+    }
+}
+
+/// Splits text into lowercase terms. Identifiers are kept whole and also split on `_`, `::` and
+/// case changes, so `despawn_recursive` and `GlobalTransform` match their parts too.
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = vec![];
+    for word in text.split(|c: char| !c.is_alphanumeric() && c != '_') {
+        if word.is_empty() {
+            continue;
+        }
+        let parts = split_identifier(word);
+        if parts.len() > 1 {
+            tokens.push(word.to_lowercase());
+        }
+        tokens.extend(parts);
+    }
+    tokens
+}
+
+fn split_identifier(word: &str) -> Vec<String> {
+    let mut parts = vec![];
+    for part in word.split('_').filter(|part| !part.is_empty()) {
+        let mut current = String::new();
+        let mut previous_lowercase = false;
+        for c in part.chars() {
+            if c.is_uppercase() && previous_lowercase {
+                parts.push(current.to_lowercase());
+                current.clear();
+            }
+            previous_lowercase = c.is_lowercase() || c.is_numeric();
+            current.push(c);
+        }
+        parts.push(current.to_lowercase());
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &str) -> DocumentEntry {
+        DocumentEntry {
+            id: path.to_string(),
+            file: String::new(),
+            path: path.to_string(),
+            kind: "struct".to_string(),
+            krate: String::new(),
+            module: String::new(),
+            public_module: String::new(),
+            deprecated: false,
+        }
+    }
+
+    #[test]
+    fn tokenize_splits_identifiers() {
+        assert_eq!(
+            tokenize("despawn_recursive GlobalTransform"),
+            [
+                "despawn_recursive",
+                "despawn",
+                "recursive",
+                "globaltransform",
+                "global",
+                "transform"
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenize_drops_punctuation() {
+        assert_eq!(
+            tokenize("Query<&Transform>, 2 times"),
+            ["query", "transform", "2", "times"]
+        );
+    }
+
+    #[test]
+    fn bm25_score() {
+        let index = LexicalIndex::from_texts(
+            vec![entry("a::Camera"), entry("b::Light")],
+            &["camera camera".to_string(), "light".to_string()],
+        );
+        let results = index.search("camera");
+
+        // "camera" appears 2 times in the text and 3 times through the path boost of the first
+        // document, 8 terms long with "a". The average length is (8 + 7) / 2.
+        let idf = ((2.0 - 1.0 + 0.5) / (1.0 + 0.5) + 1.0_f32).ln();
+        let expected = idf * 5.0 * (K1 + 1.0) / (5.0 + K1 * (1.0 - B + B * 8.0 / 7.5));
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0.path, "a::Camera");
+        assert!((results[0].1 - expected).abs() < 1e-6);
+    }
+
+    #[test]
+    fn bm25_ranks_identifier_matches_first() {
+        let index = LexicalIndex::from_texts(
+            vec![
+                entry("ecs::Commands"),
+                entry("hierarchy::DespawnRecursiveExt"),
+            ],
+            &[
+                "Commands can despawn an entity.".to_string(),
+                "Adds despawn_recursive to despawn an entity and its children.".to_string(),
+            ],
+        );
+        let results = index.search("despawn_recursive");
+        assert_eq!(results[0].0.path, "hierarchy::DespawnRecursiveExt");
+    }
+}
@@ -1,5 +1,18 @@
+pub mod answer;
+pub mod browser;
+pub mod chroma;
+pub mod collections;
+pub mod config;
+pub mod conversation;
 pub mod document;
 pub mod embed;
+pub mod eval;
+pub mod expansion;
+pub mod health;
 pub mod json_generator;
+pub mod lexical;
 pub mod ollama;
+pub mod process;
+pub mod progress;
 pub mod prompt;
+pub mod rerank;
@@ -1,36 +1,153 @@
-use std::error::Error;
+use std::{error::Error, time::Duration};
 
-use ollama_rs::{generation::embeddings::request::GenerateEmbeddingsRequest, Ollama};
+use ollama_rs::{
+    generation::{
+        completion::request::GenerationRequest, embeddings::request::GenerateEmbeddingsRequest,
+    },
+    Ollama,
+};
+use serde::{Deserialize, Serialize};
+use tokio_stream::{Stream, StreamExt};
+
+use crate::{process::ManagedProcess, progress::Progress};
+
+/// How often a pull checks if it was cancelled while Ollama doesn't report anything.
+const PULL_CANCEL_CHECK: Duration = Duration::from_millis(200);
+
+const MEGABYTE: u64 = 1024 * 1024;
+
+pub type TokenResult = Result<String, Box<dyn Error + Send + Sync>>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OllamaConfig {
+    pub host: String,
+    pub port: u16,
+    /// Ollama binary used to start a local server.
+    pub binary: String,
+    /// Where a local server stores its models, Ollama's default when not set.
+    pub models_dir: Option<String>,
+}
+
+impl OllamaConfig {
+    /// Panics when [`Self::host`] isn't a URL, see [`Self::validate`].
+    pub fn client(&self) -> Ollama {
+        Ollama::new(self.host.clone(), self.port)
+    }
+
+    /// Checks that [`Self::host`] is an http(s) URL, like `http://localhost`.
+    pub fn validate(&self) -> Result<(), String> {
+        let url = url::Url::parse(&self.host)
+            .map_err(|err| format!("Invalid Ollama host {}: {}", self.host, err))?;
+        if !matches!(url.scheme(), "http" | "https") || url.host().is_none() {
+            return Err(format!(
+                "Invalid Ollama host {}, expected a URL like http://localhost",
+                self.host
+            ));
+        }
+        Ok(())
+    }
+
+    /// Version of the running server.
+    pub async fn version(&self) -> Result<String, Box<dyn Error>> {
+        #[derive(Deserialize)]
+        struct Version {
+            version: String,
+        }
+
+        let url = format!("{}:{}/api/version", self.host, self.port);
+        let version: Version = reqwest::get(url).await?.error_for_status()?.json().await?;
+        Ok(version.version)
+    }
+
+    /// Starts `ollama serve` listening on [`Self::host`] and [`Self::port`].
+    pub fn start(&self) -> Result<ManagedProcess, Box<dyn Error>> {
+        let mut envs = vec![(
+            "OLLAMA_HOST".to_string(),
+            format!("{}:{}", self.host, self.port),
+        )];
+        if let Some(models_dir) = &self.models_dir {
+            envs.push(("OLLAMA_MODELS".to_string(), models_dir.clone()));
+        }
+        ManagedProcess::spawn(&self.binary, &["serve".to_string()], &envs)
+    }
+}
+
+impl Default for OllamaConfig {
+    fn default() -> Self {
+        Self {
+            host: "http://localhost".to_string(),
+            port: 11434,
+            binary: "ollama".to_string(),
+            models_dir: None,
+        }
+    }
+}
 
 pub struct SimpleOllama {
     ollama: Ollama,
     embedding_model: String,
+    chat_model: String,
 }
 
 impl SimpleOllama {
-    pub fn new(embedding_model: String) -> Self {
+    pub fn new(config: &OllamaConfig, embedding_model: String, chat_model: String) -> Self {
         SimpleOllama {
-            ollama: Ollama::default(),
+            ollama: config.client(),
             embedding_model,
+            chat_model,
         }
     }
 
-    pub async fn download_model(&self) -> Result<(), Box<dyn Error>> {
-        let Ok(models) = self.ollama.list_local_models().await else {
-            println!("Error generating embeddings");
-            println!("Is Ollama running?");
-            panic!();
-        };
-
-        for model in models {
-            if model.name == self.embedding_model {
-                return Ok(());
-            }
+    /// Pulls `wanted` if it's missing from Ollama, reporting the download of each layer to
+    /// `progress`.
+    pub async fn download_model(
+        &self,
+        wanted: &str,
+        progress: &Progress,
+    ) -> Result<(), Box<dyn Error>> {
+        let models =
+            self.ollama.list_local_models().await.map_err(|err| {
+                format!("Couldn't list Ollama models, is Ollama running? {}", err)
+            })?;
+        if models.iter().any(|model| model.name == wanted) {
+            return Ok(());
         }
 
-        self.ollama
-            .pull_model(self.embedding_model.clone(), false)
+        progress.set(format!("Pulling {}", wanted));
+        progress.set_ratio(None);
+        let mut stream = self
+            .ollama
+            .pull_model_stream(wanted.to_string(), false)
             .await?;
+        loop {
+            if progress.is_cancelled() {
+                return Err("Cancelled".into());
+            }
+            let status = match tokio::time::timeout(PULL_CANCEL_CHECK, stream.next()).await {
+                Ok(Some(status)) => status?,
+                Ok(None) => break,
+                Err(_) => continue,
+            };
+            match (status.digest, status.completed, status.total) {
+                (Some(digest), completed, Some(total)) if total > 0 => {
+                    let completed = completed.unwrap_or_default();
+                    let digest = digest.trim_start_matches("sha256:");
+                    progress.set(format!(
+                        "Pulling {}: layer {} {}/{} MB",
+                        wanted,
+                        &digest[..digest.len().min(12)],
+                        completed / MEGABYTE,
+                        total / MEGABYTE
+                    ));
+                    progress.set_ratio(Some(completed as f64 / total as f64));
+                }
+                _ => {
+                    progress.set(format!("Pulling {}: {}", wanted, status.message));
+                    progress.set_ratio(None);
+                }
+            }
+        }
 
         Ok(())
     }
@@ -40,4 +157,29 @@ impl SimpleOllama {
         let mut res = self.ollama.generate_embeddings(request).await?;
         Ok(res.embeddings.remove(0))
     }
+
+    pub async fn generate(&self, system: &str, prompt: &str) -> Result<String, Box<dyn Error>> {
+        let request = GenerationRequest::new(self.chat_model.clone(), prompt.to_string())
+            .system(system.to_string());
+        let res = self.ollama.generate(request).await?;
+        Ok(res.response)
+    }
+
+    /// Same as [`Self::generate`], yielding the response as it is produced.
+    pub async fn generate_stream(
+        &self,
+        system: &str,
+        prompt: &str,
+    ) -> Result<impl Stream<Item = TokenResult> + Send + Unpin, Box<dyn Error>> {
+        let request = GenerationRequest::new(self.chat_model.clone(), prompt.to_string())
+            .system(system.to_string());
+        let stream = self.ollama.generate_stream(request).await?;
+        Ok(stream.map(|responses| match responses {
+            Ok(responses) => Ok(responses
+                .into_iter()
+                .map(|response| response.response)
+                .collect()),
+            Err(()) => Err("Ollama stopped streaming the response".into()),
+        }))
+    }
 }
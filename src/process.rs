@@ -0,0 +1,92 @@
+use std::{
+    error::Error,
+    fmt,
+    process::Stdio,
+    sync::{Arc, Mutex},
+};
+
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, BufReader},
+    process::{Child, Command},
+};
+
+const MAX_LOG_LINES: usize = 1000;
+
+/// A service started by us, with its output kept to be displayed. It is killed when dropped if
+/// it wasn't stopped before.
+pub struct ManagedProcess {
+    child: Child,
+    logs: Arc<Mutex<Vec<String>>>,
+}
+
+impl ManagedProcess {
+    /// Starts `program`, must be called from within a tokio runtime.
+    pub fn spawn(
+        program: &str,
+        args: &[String],
+        envs: &[(String, String)],
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut child = Command::new(program)
+            .args(args)
+            .envs(envs.iter().cloned())
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|err| format!("Couldn't start {}: {}", program, err))?;
+
+        let logs = Arc::new(Mutex::new(vec![]));
+        if let Some(stdout) = child.stdout.take() {
+            tokio::spawn(collect(stdout, logs.clone()));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            tokio::spawn(collect(stderr, logs.clone()));
+        }
+        Ok(Self { child, logs })
+    }
+
+    /// The last lines written by the process, oldest first.
+    pub fn logs(&self) -> Vec<String> {
+        self.logs.lock().unwrap().clone()
+    }
+
+    /// How the process ended, if it did.
+    pub fn exit_status(&mut self) -> Option<String> {
+        match self.child.try_wait() {
+            Ok(Some(status)) => Some(status.to_string()),
+            Ok(None) => None,
+            Err(err) => Some(err.to_string()),
+        }
+    }
+
+    pub fn stop(&mut self) {
+        let _ = self.child.start_kill();
+    }
+}
+
+/// A command that exited unsuccessfully, with the last lines it wrote.
+#[derive(Debug)]
+pub struct ProcessFailed {
+    pub message: String,
+    pub output: Vec<String>,
+}
+
+impl fmt::Display for ProcessFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for ProcessFailed {}
+
+pub(crate) async fn collect(output: impl AsyncRead + Unpin, logs: Arc<Mutex<Vec<String>>>) {
+    let mut lines = BufReader::new(output).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let mut logs = logs.lock().unwrap();
+        if logs.len() == MAX_LOG_LINES {
+            logs.remove(0);
+        }
+        logs.push(line);
+    }
+}
@@ -0,0 +1,40 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Mutex,
+};
+
+/// Shared between a long running task and whoever displays it, which can also cancel it.
+#[derive(Debug, Default)]
+pub struct Progress {
+    message: Mutex<String>,
+    /// Done fraction of the current step, between 0 and 1, when it is known.
+    ratio: Mutex<Option<f64>>,
+    cancelled: AtomicBool,
+}
+
+impl Progress {
+    pub fn set(&self, message: impl Into<String>) {
+        *self.message.lock().unwrap() = message.into();
+    }
+
+    pub fn message(&self) -> String {
+        self.message.lock().unwrap().clone()
+    }
+
+    pub fn set_ratio(&self, ratio: Option<f64>) {
+        *self.ratio.lock().unwrap() = ratio.map(|ratio| ratio.clamp(0.0, 1.0));
+    }
+
+    pub fn ratio(&self) -> Option<f64> {
+        *self.ratio.lock().unwrap()
+    }
+
+    /// Asks the task to stop at its next check.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
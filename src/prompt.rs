@@ -1,31 +1,765 @@
-use chromadb::v2::{collection::QueryOptions, ChromaClient};
+use std::collections::HashMap;
 
-use crate::ollama::SimpleOllama;
+use chromadb::v2::collection::{ChromaCollection, GetOptions, QueryOptions};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::{
+    chroma::ChromaConfig,
+    collections,
+    document::{DocumentEntry, MODULE_KEY_PREFIX},
+    expansion::{expand_query, hypothetical_document, rewrite_identifiers},
+    lexical::LexicalIndex,
+    ollama::SimpleOllama,
+    rerank::{rerank, Reranker},
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetrievedDocument {
+    pub id: String,
+    pub path: String,
+    pub kind: String,
+    pub krate: String,
+    pub module: String,
+    pub deprecated: bool,
+    pub text: String,
+    pub distance: f32,
+    /// Similarity between 0 and 1 derived from `distance`, comparable across distance functions.
+    pub similarity: f32,
+    /// Reciprocal rank fusion score, set by hybrid retrieval.
+    pub fusion_score: Option<f32>,
+    /// Score given by the re-ranking stage, which decided the final order when set.
+    pub rerank_score: Option<f32>,
+}
+
+impl RetrievedDocument {
+    fn new(
+        id: String,
+        text: String,
+        metadata: Option<&Map<String, Value>>,
+        distance: f32,
+        space: &str,
+    ) -> Self {
+        let field = |name: &str| {
+            metadata
+                .and_then(|metadata| metadata.get(name))
+                .and_then(|value| value.as_str())
+                .map(|value| value.to_string())
+        };
+        let path = field("path").unwrap_or_else(|| id.clone());
+        RetrievedDocument {
+            kind: field("kind").unwrap_or_default(),
+            krate: field("crate")
+                .unwrap_or_else(|| path.split("::").next().unwrap_or_default().to_string()),
+            module: field("module").unwrap_or_else(|| {
+                path.rsplit_once("::")
+                    .map(|(module, _)| module.to_string())
+                    .unwrap_or_default()
+            }),
+            deprecated: metadata
+                .and_then(|metadata| metadata.get("deprecated"))
+                .and_then(|value| value.as_bool())
+                .unwrap_or_default(),
+            path,
+            id,
+            text,
+            distance,
+            similarity: similarity(space, distance),
+            fusion_score: None,
+            rerank_score: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetrieveOptions {
+    pub top_k: usize,
+    /// Documents further than this from the prompt are dropped.
+    pub max_distance: Option<f32>,
+    /// Documents with a lower [`RetrievedDocument::similarity`] are dropped.
+    pub min_similarity: Option<f32>,
+    pub filter: RetrieveFilter,
+    /// Share of the BM25 keyword ranking when fusing it with the vector ranking, from 0 for
+    /// vector search only to 1 for keyword search only.
+    pub lexical_weight: f32,
+    /// Re-orders a larger set of `candidates` before keeping the `top_k` best.
+    pub rerank: Option<Reranker>,
+    pub candidates: usize,
+    /// Picks the `top_k` documents from the candidates with maximal marginal relevance, trading
+    /// relevance (1) against diversity (0).
+    pub mmr_lambda: Option<f32>,
+    /// Embeds hypothetical documentation generated from the prompt instead of the prompt (HyDE).
+    pub hyde: bool,
+    /// Number of rephrasings of the prompt to retrieve for, fused with the prompt's results.
+    pub expansions: usize,
+    /// Adds the document-like phrasing of the prompt's code identifiers before searching.
+    pub rewrite_identifiers: bool,
+}
+
+/// Restricts retrieval to documents matching every set field.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RetrieveFilter {
+    pub krate: Option<String>,
+    pub kind: Option<String>,
+    /// Path of a module, matching items in it and in its submodules, by their defining path
+    /// (`bevy_render::camera`) or the path they are re-exported at (`bevy::render::camera`).
+    pub module: Option<String>,
+    pub deprecated: Option<bool>,
+}
+
+impl RetrieveFilter {
+    /// The `where` clause of the filter.
+    fn as_where(&self) -> Option<Value> {
+        let mut conditions = vec![];
+        if let Some(krate) = &self.krate {
+            conditions.push(json!({ "crate": { "$eq": krate } }));
+        }
+        if let Some(kind) = &self.kind {
+            conditions.push(json!({ "kind": { "$eq": kind } }));
+        }
+        if let Some(module) = &self.module {
+            let key = format!("{}{}", MODULE_KEY_PREFIX, module);
+            conditions.push(json!({ key: { "$eq": true } }));
+        }
+        if let Some(deprecated) = self.deprecated {
+            conditions.push(json!({ "deprecated": { "$eq": deprecated } }));
+        }
+        match conditions.len() {
+            0 => None,
+            1 => conditions.pop(),
+            _ => Some(json!({ "$and": conditions })),
+        }
+    }
+
+    pub fn matches(&self, document: &DocumentEntry) -> bool {
+        self.krate
+            .as_ref()
+            .is_none_or(|filter| *filter == document.krate)
+            && self
+                .kind
+                .as_ref()
+                .is_none_or(|filter| *filter == document.kind)
+            && self
+                .module
+                .as_ref()
+                .is_none_or(|filter| document.modules().contains(filter))
+            && self
+                .deprecated
+                .is_none_or(|filter| filter == document.deprecated)
+    }
+}
+
+impl Default for RetrieveOptions {
+    fn default() -> Self {
+        Self {
+            top_k: 10,
+            max_distance: None,
+            min_similarity: None,
+            filter: RetrieveFilter::default(),
+            lexical_weight: 0.0,
+            rerank: None,
+            candidates: 50,
+            mmr_lambda: None,
+            hyde: false,
+            expansions: 0,
+            rewrite_identifiers: false,
+        }
+    }
+}
+
+impl RetrieveOptions {
+    fn keeps(&self, document: &RetrievedDocument) -> bool {
+        self.max_distance
+            .is_none_or(|max_distance| document.distance <= max_distance)
+            && self
+                .min_similarity
+                .is_none_or(|min_similarity| document.similarity >= min_similarity)
+    }
+
+    /// How many results to ask Chroma for, leaving room for re-ranking.
+    fn n_results(&self) -> usize {
+        if self.rerank.is_some() || self.mmr_lambda.is_some() {
+            self.candidates.max(self.top_k)
+        } else {
+            self.top_k
+        }
+    }
+}
+
+/// Formats the options in the syntax read by [`Query::parse`].
+impl std::fmt::Display for RetrieveOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "top_k:{}", self.top_k)?;
+        if let Some(max_distance) = self.max_distance {
+            write!(f, " max_distance:{}", max_distance)?;
+        }
+        if let Some(min_similarity) = self.min_similarity {
+            write!(f, " min_similarity:{}", min_similarity)?;
+        }
+        if let Some(krate) = &self.filter.krate {
+            write!(f, " crate:{}", krate)?;
+        }
+        if let Some(kind) = &self.filter.kind {
+            write!(f, " kind:{}", kind)?;
+        }
+        if let Some(module) = &self.filter.module {
+            write!(f, " module:{}", module)?;
+        }
+        if let Some(deprecated) = self.filter.deprecated {
+            write!(f, " deprecated:{}", deprecated)?;
+        }
+        if self.lexical_weight > 0.0 {
+            write!(f, " lexical:{}", self.lexical_weight)?;
+        }
+        if let Some(rerank) = self.rerank {
+            write!(f, " rerank:{}", rerank)?;
+        }
+        if let Some(mmr_lambda) = self.mmr_lambda {
+            write!(f, " mmr:{}", mmr_lambda)?;
+        }
+        if self.rerank.is_some() || self.mmr_lambda.is_some() {
+            write!(f, " candidates:{}", self.candidates)?;
+        }
+        if self.hyde {
+            write!(f, " hyde:true")?;
+        }
+        if self.expansions > 0 {
+            write!(f, " expand:{}", self.expansions)?;
+        }
+        if self.rewrite_identifiers {
+            write!(f, " identifiers:true")?;
+        }
+        Ok(())
+    }
+}
+
+/// A prompt with its retrieve options, parsed from text where leading `option:value` words
+/// override the defaults, for example `top_k:5 kind:trait lexical:0.5 how to spawn a camera`.
+/// An empty value (`crate:`) clears a filter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    pub text: String,
+    pub options: RetrieveOptions,
+}
+
+impl Query {
+    pub fn parse(input: &str, defaults: &RetrieveOptions) -> Self {
+        let mut options = defaults.clone();
+        let mut words = input.split_whitespace().peekable();
+        while let Some(word) = words.peek() {
+            let Some((option, value)) = word.split_once(':') else {
+                break;
+            };
+            let parsed = match option {
+                "top_k" => value.parse().map(|top_k| options.top_k = top_k).is_ok(),
+                "max_distance" => value
+                    .parse()
+                    .map(|max_distance| options.max_distance = Some(max_distance))
+                    .is_ok(),
+                "min_similarity" => value
+                    .parse()
+                    .map(|min_similarity| options.min_similarity = Some(min_similarity))
+                    .is_ok(),
+                "crate" => {
+                    options.filter.krate = (!value.is_empty()).then(|| value.to_string());
+                    true
+                }
+                "kind" => {
+                    options.filter.kind = (!value.is_empty()).then(|| value.to_string());
+                    true
+                }
+                "module" => {
+                    options.filter.module = (!value.is_empty()).then(|| value.to_string());
+                    true
+                }
+                "deprecated" => value
+                    .parse()
+                    .map(|deprecated| options.filter.deprecated = Some(deprecated))
+                    .is_ok(),
+                "rerank" if value == "none" => {
+                    options.rerank = None;
+                    true
+                }
+                "rerank" => value
+                    .parse()
+                    .map(|reranker| options.rerank = Some(reranker))
+                    .is_ok(),
+                "mmr" if value == "none" => {
+                    options.mmr_lambda = None;
+                    true
+                }
+                "mmr" => value
+                    .parse::<f32>()
+                    .map(|lambda| options.mmr_lambda = Some(lambda.clamp(0.0, 1.0)))
+                    .is_ok(),
+                "hyde" => value.parse().map(|hyde| options.hyde = hyde).is_ok(),
+                "expand" => value
+                    .parse()
+                    .map(|expansions| options.expansions = expansions)
+                    .is_ok(),
+                "identifiers" => value
+                    .parse()
+                    .map(|rewrite| options.rewrite_identifiers = rewrite)
+                    .is_ok(),
+                "candidates" => value
+                    .parse()
+                    .map(|candidates| options.candidates = candidates)
+                    .is_ok(),
+                "lexical" => value
+                    .parse::<f32>()
+                    .map(|weight| options.lexical_weight = weight.clamp(0.0, 1.0))
+                    .is_ok(),
+                _ => false,
+            };
+            if !parsed {
+                break;
+            }
+            words.next();
+        }
+        Query {
+            text: words.collect::<Vec<_>>().join(" "),
+            options,
+        }
+    }
+}
+
+/// Maps a Chroma distance to a similarity between 0 and 1, depending on the `hnsw:space` of the
+/// collection. The inner product distance is `1 - dot`, which only stays between 0 and 2 for
+/// normalized embeddings, as produced by the usual embedding models; others are clamped.
+pub fn similarity(space: &str, distance: f32) -> f32 {
+    match space {
+        "cosine" | "ip" => (1.0 - distance / 2.0).clamp(0.0, 1.0),
+        _ => 1.0 / (1.0 + distance.max(0.0)),
+    }
+}
 
 pub async fn retrieve(
-    ollama: SimpleOllama,
+    ollama: &SimpleOllama,
+    chroma: &ChromaConfig,
     collection_name: &str,
     prompt: &str,
-) -> Result<Vec<(String, f32)>, Box<dyn std::error::Error>> {
-    let chroma = ChromaClient::new(Default::default());
+    options: &RetrieveOptions,
+) -> Result<Vec<RetrievedDocument>, Box<dyn std::error::Error>> {
+    let chroma = chroma.client()?;
 
     let collection = chroma.get_collection(&collection_name).await?;
+    let space = collections::space(&collection).unwrap_or("l2").to_string();
+
+    // Rewrites and expansions only help the embeddings match, lexical search and re-ranking get
+    // the prompt as written.
+    let vector_prompt = if options.rewrite_identifiers {
+        rewrite_identifiers(prompt)
+    } else {
+        prompt.to_string()
+    };
+    let mut queries = vec![vector_prompt.clone()];
+    if options.expansions > 0 {
+        queries.extend(expand_query(ollama, &vector_prompt, options.expansions).await?);
+    }
+
+    let embeddings = ollama.embeddings(&vector_prompt).await?;
+    let mut rankings = vec![];
+    for query in &queries {
+        let query_embeddings = if options.hyde {
+            ollama
+                .embeddings(&hypothetical_document(ollama, query).await?)
+                .await?
+        } else if *query == vector_prompt {
+            embeddings.clone()
+        } else {
+            ollama.embeddings(query).await?
+        };
+        rankings.push((
+            (1.0 - options.lexical_weight) / queries.len() as f32,
+            vector_search(&collection, &space, query_embeddings, options).await?,
+        ));
+    }
+    if options.lexical_weight > 0.0 {
+        rankings.push((
+            options.lexical_weight,
+            lexical_search(&collection, &space, &embeddings, prompt, options).await?,
+        ));
+    }
+    let retrieved = if rankings.len() > 1 {
+        fuse(rankings)
+    } else {
+        rankings
+            .pop()
+            .map(|(_, ranking)| ranking)
+            .unwrap_or_default()
+    };
+
+    let mut retrieved = retrieved
+        .into_iter()
+        .filter(|document| options.keeps(document))
+        .take(options.n_results())
+        .collect::<Vec<_>>();
+    if let Some(reranker) = options.rerank {
+        retrieved = rerank(reranker, ollama, prompt, retrieved).await?;
+    }
+    if let Some(lambda) = options.mmr_lambda {
+        let ids = retrieved
+            .iter()
+            .map(|document| document.id.clone())
+            .collect();
+        let candidates = stored_embeddings(&collection, ids).await?;
+        retrieved =
+            maximal_marginal_relevance(retrieved, &candidates, &embeddings, lambda, options.top_k);
+    }
+    retrieved.truncate(options.top_k);
+    Ok(retrieved)
+}
+
+async fn stored_embeddings(
+    collection: &ChromaCollection,
+    ids: Vec<String>,
+) -> Result<HashMap<String, Vec<f32>>, Box<dyn std::error::Error>> {
+    if ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let result = collection
+        .get(GetOptions {
+            ids,
+            include: Some(vec!["embeddings".into()]),
+            ..Default::default()
+        })
+        .await?;
+    let embeddings = result.embeddings.unwrap_or_default();
+    Ok(result
+        .ids
+        .into_iter()
+        .zip(embeddings)
+        .filter_map(|(id, embedding)| Some((id, embedding?)))
+        .collect())
+}
+
+/// Greedily picks the document maximizing `lambda * relevance - (1 - lambda) * redundancy`,
+/// where redundancy is its highest cosine similarity to an already picked document. Relevance
+/// is the re-ranking score when the candidates were re-ranked, their cosine similarity to the
+/// prompt otherwise, scaled to [0, 1] so that `lambda` weighs both terms alike. Candidates
+/// without a stored embedding use their query similarity and are never redundant.
+fn maximal_marginal_relevance(
+    candidates: Vec<RetrievedDocument>,
+    embeddings: &HashMap<String, Vec<f32>>,
+    prompt: &[f32],
+    lambda: f32,
+    top_k: usize,
+) -> Vec<RetrievedDocument> {
+    let cosine = |a: &[f32], b: &[f32]| 1.0 - distance("cosine", a, b);
+    let reranked = candidates
+        .iter()
+        .any(|document| document.rerank_score.is_some());
+    let relevance = candidates
+        .iter()
+        .map(|document| match embeddings.get(&document.id) {
+            _ if reranked => document.rerank_score.unwrap_or_default(),
+            Some(embedding) => cosine(prompt, embedding),
+            None => document.similarity,
+        })
+        .collect::<Vec<_>>();
+    let min = relevance.iter().copied().fold(f32::INFINITY, f32::min);
+    let max = relevance.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let mut candidates = candidates
+        .into_iter()
+        .zip(relevance.into_iter().map(|relevance| {
+            if max > min {
+                (relevance - min) / (max - min)
+            } else {
+                1.0
+            }
+        }))
+        .collect::<Vec<_>>();
 
-    let embeddings = ollama.embeddings(prompt).await?;
+    let mut picked: Vec<RetrievedDocument> = vec![];
+    while picked.len() < top_k && !candidates.is_empty() {
+        let score = |(document, relevance): &(RetrievedDocument, f32)| {
+            let redundancy = embeddings.get(&document.id).map_or(0.0, |embedding| {
+                picked
+                    .iter()
+                    .filter_map(|picked| embeddings.get(&picked.id))
+                    .map(|picked| cosine(embedding, picked))
+                    .fold(0.0, f32::max)
+            });
+            lambda * relevance - (1.0 - lambda) * redundancy
+        };
+        let best = candidates
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| score(a).total_cmp(&score(b)))
+            .map(|(i, _)| i)
+            .unwrap();
+        picked.push(candidates.remove(best).0);
+    }
+    picked
+}
+
+/// Nearest documents to `embeddings` in the collection.
+async fn vector_search(
+    collection: &ChromaCollection,
+    space: &str,
+    embeddings: Vec<f32>,
+    options: &RetrieveOptions,
+) -> Result<Vec<RetrievedDocument>, Box<dyn std::error::Error>> {
     let query = QueryOptions {
         query_embeddings: Some(vec![embeddings]),
-        n_results: Some(10),
-        include: Some(vec!["distances"]),
+        n_results: Some(options.n_results()),
+        include: Some(vec!["distances", "documents", "metadatas"]),
+        where_metadata: options.filter.as_where(),
         ..Default::default()
     };
     let result = collection.query(query, None).await?;
-    Ok(result.ids[0]
+    let distances = result.distances.unwrap_or_default();
+    let documents = result.documents.unwrap_or_default();
+    let metadatas = result.metadatas.unwrap_or_default();
+    let ids = result.ids.first().map(Vec::as_slice).unwrap_or_default();
+    Ok(ids
         .iter()
         .enumerate()
-        .map(|(i, doc)| {
-            let mut doc = doc.clone();
-            let _ = doc.split_off(doc.len() - 3);
-            (doc, result.distances.as_ref().unwrap()[0][i])
+        // Without a distance, a document can't be ranked against the others.
+        .filter_map(|(i, id)| {
+            let distance = *distances.first().and_then(|distances| distances.get(i))?;
+            Some(RetrievedDocument::new(
+                id.clone(),
+                documents
+                    .first()
+                    .and_then(|documents| documents.get(i))
+                    .cloned()
+                    .unwrap_or_default(),
+                metadatas
+                    .first()
+                    .and_then(|metadatas| metadatas.get(i))
+                    .and_then(|metadata| metadata.as_ref()),
+                distance,
+                space,
+            ))
         })
         .collect())
 }
+
+/// Best BM25 matches for `prompt`, with their text and distance to the prompt read back from
+/// Chroma so they can be filtered like vector results.
+async fn lexical_search(
+    collection: &ChromaCollection,
+    space: &str,
+    embeddings: &[f32],
+    prompt: &str,
+    options: &RetrieveOptions,
+) -> Result<Vec<RetrievedDocument>, Box<dyn std::error::Error>> {
+    // Without an index, retrieval falls back to the vector ranking.
+    let Some(index) = LexicalIndex::cached()? else {
+        return Ok(vec![]);
+    };
+    let ids = index
+        .search(prompt)
+        .into_iter()
+        .filter(|(document, _)| options.filter.matches(document))
+        .take(options.n_results())
+        .map(|(document, _)| document.id.clone())
+        .collect::<Vec<_>>();
+    if ids.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let result = collection
+        .get(GetOptions {
+            ids: ids.clone(),
+            include: Some(vec![
+                "documents".into(),
+                "metadatas".into(),
+                "embeddings".into(),
+            ]),
+            ..Default::default()
+        })
+        .await?;
+    let documents = result.documents.unwrap_or_default();
+    let metadatas = result.metadatas.unwrap_or_default();
+    let stored_embeddings = result.embeddings.unwrap_or_default();
+    let mut found = result
+        .ids
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, id)| {
+            let stored = stored_embeddings.get(i)?.as_ref()?;
+            Some(RetrievedDocument::new(
+                id,
+                documents.get(i).cloned().flatten().unwrap_or_default(),
+                metadatas.get(i).and_then(|metadata| metadata.as_ref()),
+                distance(space, embeddings, stored),
+                space,
+            ))
+        })
+        .collect::<Vec<_>>();
+    // Chroma doesn't keep the order of the requested ids.
+    found.sort_by_key(|document| ids.iter().position(|id| *id == document.id));
+    Ok(found)
+}
+
+/// Weighted reciprocal rank fusion of rankings from the vector searches and the lexical search.
+fn fuse(rankings: Vec<(f32, Vec<RetrievedDocument>)>) -> Vec<RetrievedDocument> {
+    const K: f32 = 60.0;
+    let mut fused: Vec<RetrievedDocument> = vec![];
+    for (weight, ranking) in rankings {
+        for (rank, mut document) in ranking.into_iter().enumerate() {
+            let score = weight / (K + rank as f32 + 1.0);
+            match fused.iter_mut().find(|fused| fused.id == document.id) {
+                Some(fused) => *fused.fusion_score.get_or_insert(0.0) += score,
+                None => {
+                    document.fusion_score = Some(score);
+                    fused.push(document);
+                }
+            }
+        }
+    }
+    fused.sort_by(|a, b| {
+        b.fusion_score
+            .unwrap_or_default()
+            .total_cmp(&a.fusion_score.unwrap_or_default())
+    });
+    fused
+}
+
+/// The distance Chroma would compute between two embeddings in the given `hnsw:space`.
+fn distance(space: &str, a: &[f32], b: &[f32]) -> f32 {
+    let dot = a.iter().zip(b).map(|(a, b)| a * b).sum::<f32>();
+    match space {
+        "ip" => 1.0 - dot,
+        "cosine" => {
+            let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+            1.0 - dot / (norm(a) * norm(b)).max(f32::EPSILON)
+        }
+        _ => a.iter().zip(b).map(|(a, b)| (a - b) * (a - b)).sum(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(id: &str, similarity: f32) -> RetrievedDocument {
+        RetrievedDocument {
+            id: id.to_string(),
+            path: id.to_string(),
+            kind: String::new(),
+            krate: String::new(),
+            module: String::new(),
+            deprecated: false,
+            text: String::new(),
+            distance: 1.0 - similarity,
+            similarity,
+            fusion_score: None,
+            rerank_score: None,
+        }
+    }
+
+    fn ids(documents: &[RetrievedDocument]) -> Vec<&str> {
+        documents
+            .iter()
+            .map(|document| document.id.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn parse_clears_a_filter_with_an_empty_value() {
+        let defaults = RetrieveOptions {
+            filter: RetrieveFilter {
+                krate: Some("bevy_ecs".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let query = Query::parse("crate: how to spawn", &defaults);
+        assert_eq!(query.options.filter.krate, None);
+        assert_eq!(query.text, "how to spawn");
+    }
+
+    #[test]
+    fn parse_stops_at_the_first_word_that_isnt_an_option() {
+        let query = Query::parse(
+            "top_k:5 kind:trait lexical:2 bevy::prelude::Query kind:struct",
+            &RetrieveOptions::default(),
+        );
+        assert_eq!(query.options.top_k, 5);
+        assert_eq!(query.options.filter.kind.as_deref(), Some("trait"));
+        assert_eq!(query.options.lexical_weight, 1.0);
+        assert_eq!(query.text, "bevy::prelude::Query kind:struct");
+
+        let query = Query::parse("top_k:many things", &RetrieveOptions::default());
+        assert_eq!(query.options, RetrieveOptions::default());
+        assert_eq!(query.text, "top_k:many things");
+    }
+
+    #[test]
+    fn filter_as_where() {
+        assert_eq!(RetrieveFilter::default().as_where(), None);
+
+        let kind = RetrieveFilter {
+            kind: Some("trait".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(kind.as_where(), Some(json!({ "kind": { "$eq": "trait" } })));
+
+        let filter = RetrieveFilter {
+            krate: Some("bevy_ecs".to_string()),
+            module: Some("bevy::ecs".to_string()),
+            deprecated: Some(false),
+            ..Default::default()
+        };
+        let module_key = format!("{}bevy::ecs", MODULE_KEY_PREFIX);
+        assert_eq!(
+            filter.as_where(),
+            Some(json!({ "$and": [
+                { "crate": { "$eq": "bevy_ecs" } },
+                { module_key: { "$eq": true } },
+                { "deprecated": { "$eq": false } },
+            ] }))
+        );
+    }
+
+    #[test]
+    fn fuse_keeps_the_order_when_a_ranking_is_empty() {
+        let fused = fuse(vec![
+            (0.5, vec![document("a", 0.9), document("b", 0.8)]),
+            (0.5, vec![]),
+        ]);
+        assert_eq!(ids(&fused), ["a", "b"]);
+        assert_eq!(fused[0].fusion_score, Some(0.5 / 61.0));
+        assert_eq!(fused[1].fusion_score, Some(0.5 / 62.0));
+    }
+
+    #[test]
+    fn fuse_sums_the_weighted_ranks() {
+        let fused = fuse(vec![
+            (0.25, vec![document("a", 0.9), document("b", 0.8)]),
+            (0.75, vec![document("c", 0.5), document("b", 0.4)]),
+        ]);
+        assert_eq!(ids(&fused), ["b", "c", "a"]);
+        assert_eq!(fused[0].fusion_score, Some(0.25 / 62.0 + 0.75 / 62.0));
+    }
+
+    /// `b` nearly duplicates `a`, `c` is unrelated to both and to the prompt.
+    fn mmr_candidates() -> (Vec<RetrievedDocument>, HashMap<String, Vec<f32>>) {
+        let candidates = vec![document("a", 0.9), document("b", 0.8), document("c", 0.1)];
+        let embeddings = HashMap::from([
+            ("a".to_string(), vec![1.0, 0.0]),
+            ("b".to_string(), vec![0.99, 0.14]),
+            ("c".to_string(), vec![0.0, 1.0]),
+        ]);
+        (candidates, embeddings)
+    }
+
+    #[test]
+    fn mmr_with_lambda_1_keeps_the_relevance_order() {
+        let (candidates, embeddings) = mmr_candidates();
+        let picked = maximal_marginal_relevance(candidates, &embeddings, &[1.0, 0.0], 1.0, 3);
+        assert_eq!(ids(&picked), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn mmr_with_lambda_0_skips_near_duplicates() {
+        let (candidates, embeddings) = mmr_candidates();
+        let picked = maximal_marginal_relevance(candidates, &embeddings, &[1.0, 0.0], 0.0, 2);
+        let mut picked = ids(&picked);
+        picked.sort();
+        assert_eq!(picked, ["a", "c"]);
+    }
+}
@@ -0,0 +1,143 @@
+use std::{collections::HashSet, error::Error, str::FromStr};
+
+use futures::{stream, StreamExt};
+
+use crate::{lexical::tokenize, ollama::SimpleOllama, prompt::RetrievedDocument};
+
+const SYSTEM: &str = "You judge how relevant a piece of Rust documentation is to a question. \
+Reply with a single number from 0 (unrelated) to 10 (answers the question), nothing else.";
+
+/// Documents graded by the chat model, one call each. The others keep their order after them.
+const LLM_RERANK_LIMIT: usize = 15;
+/// Grading calls sent to Ollama at once.
+const LLM_RERANK_CONCURRENCY: usize = 4;
+
+// Weights of the heuristic score, summing to 1. The similarity carries most of the signal. A
+// prompt naming the item is a strong hint it's the one asked about, a match elsewhere in the path
+// a weaker one. The kind prior only breaks ties.
+const SIMILARITY_WEIGHT: f32 = 0.5;
+const NAME_WEIGHT: f32 = 0.3;
+const PATH_WEIGHT: f32 = 0.15;
+const KIND_WEIGHT: f32 = 0.05;
+/// Puts a deprecated item after a current one of comparable score.
+const DEPRECATED_PENALTY: f32 = 0.1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reranker {
+    /// Scores documents on their similarity and on how well their item name and path match the
+    /// prompt, with a small prior on the item kind.
+    Heuristic,
+    /// Asks the chat model to grade each document against the prompt.
+    Llm,
+}
+
+impl FromStr for Reranker {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "heuristic" => Ok(Reranker::Heuristic),
+            "llm" => Ok(Reranker::Llm),
+            _ => Err(format!("Unknown reranker {}", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for Reranker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Reranker::Heuristic => write!(f, "heuristic"),
+            Reranker::Llm => write!(f, "llm"),
+        }
+    }
+}
+
+/// Sets [`RetrievedDocument::rerank_score`] and sorts documents by it, best first. The LLM
+/// reranker only grades the first [`LLM_RERANK_LIMIT`] documents.
+pub async fn rerank(
+    reranker: Reranker,
+    ollama: &SimpleOllama,
+    prompt: &str,
+    mut documents: Vec<RetrievedDocument>,
+) -> Result<Vec<RetrievedDocument>, Box<dyn Error>> {
+    match reranker {
+        Reranker::Heuristic => {
+            for document in documents.iter_mut() {
+                document.rerank_score = Some(heuristic_score(prompt, document));
+            }
+        }
+        Reranker::Llm => {
+            let graded = documents.len().min(LLM_RERANK_LIMIT);
+            let scores = stream::iter(documents[..graded].iter().enumerate())
+                .map(|(i, document)| async move {
+                    let score = llm_score(ollama, prompt, document)
+                        .await
+                        .map_err(|err| err.to_string());
+                    (i, score)
+                })
+                .buffer_unordered(LLM_RERANK_CONCURRENCY)
+                .collect::<Vec<_>>()
+                .await;
+            for (i, score) in scores {
+                documents[i].rerank_score = Some(score?);
+            }
+        }
+    }
+    // Stable, so documents left ungraded stay after the graded ones, in their order.
+    documents.sort_by(|a, b| {
+        b.rerank_score
+            .unwrap_or_default()
+            .total_cmp(&a.rerank_score.unwrap_or_default())
+    });
+    Ok(documents)
+}
+
+fn heuristic_score(prompt: &str, document: &RetrievedDocument) -> f32 {
+    let prompt = tokenize(prompt).into_iter().collect::<HashSet<_>>();
+    let overlap = |text: &str| {
+        let tokens = tokenize(text).into_iter().collect::<HashSet<_>>();
+        if tokens.is_empty() {
+            return 0.0;
+        }
+        tokens.intersection(&prompt).count() as f32 / tokens.len() as f32
+    };
+    let name = document.path.rsplit("::").next().unwrap_or_default();
+    let kind_prior = match document.kind.as_str() {
+        "struct" | "trait" => 1.0,
+        "enum" => 0.8,
+        _ => 0.5,
+    };
+    let deprecated_penalty = if document.deprecated {
+        DEPRECATED_PENALTY
+    } else {
+        0.0
+    };
+
+    SIMILARITY_WEIGHT * document.similarity
+        + NAME_WEIGHT * overlap(name)
+        + PATH_WEIGHT * overlap(&document.path)
+        + KIND_WEIGHT * kind_prior
+        - deprecated_penalty
+}
+
+async fn llm_score(
+    ollama: &SimpleOllama,
+    prompt: &str,
+    document: &RetrievedDocument,
+) -> Result<f32, Box<dyn Error>> {
+    let grade = ollama
+        .generate(
+            SYSTEM,
+            &format!(
+                "Question: {}\n\nDocumentation of {}:\n{}",
+                prompt, document.path, document.text
+            ),
+        )
+        .await?;
+    // Models sometimes wrap the grade in words, use the first number found.
+    let grade = grade
+        .split(|c: char| !c.is_ascii_digit() && c != '.')
+        .find_map(|word| word.parse::<f32>().ok())
+        .unwrap_or_default();
+    Ok((grade / 10.0).clamp(0.0, 1.0))
+}